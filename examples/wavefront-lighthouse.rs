@@ -24,6 +24,6 @@ fn print_information(name: &str, model: &Model) {
     println!("vertices: {}", model.mesh.vertices.len());
     println!("indices: {}", model.mesh.indices.len());
     println!("triangles: {}", model.mesh.triangles().count());
-    println!("");
+    println!();
 }
 
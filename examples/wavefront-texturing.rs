@@ -24,7 +24,7 @@ fn main() {
             };
 
             Some(Door {
-                ambient_color: ambient_color,
+                ambient_color,
                 name: obj.name().to_owned(),
                 model: Model::new(obj).unwrap(),
             })
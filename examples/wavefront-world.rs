@@ -26,7 +26,7 @@ fn main() {
 
     // Skip every second triangle if that's your kind of thing.
     let half_triangles = entire_world.mesh.triangles().enumerate().filter(|&(idx,_)| idx%2 == 0).map(|(_,t)| t);
-    let half_world: Model = Model { mesh: half_triangles.collect() };
+    let half_world: Model = Model::from_mesh(half_triangles.collect());
 
     println!("half world: {:?}", half_world);
 }
@@ -0,0 +1,267 @@
+//! Derive macros for the `mash` crate.
+//!
+//! Enable the `derive` feature of `mash` rather than depending on this
+//! crate directly; the macros are re-exported as `mash::Vertex`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Member};
+
+/// Implements `mash::Vertex` and the attribute traits for a struct.
+///
+/// Fields are marked with the attribute they hold:
+///
+/// * `#[mash(position)]`, which is required, implements `Vertex`.
+/// * `#[mash(normal)]` implements `HasNormal`.
+/// * `#[mash(texture_coords)]` implements `HasTexCoords`.
+/// * `#[mash(color)]` implements `HasColor`.
+/// * `#[mash(tangent)]` implements `HasTangent`.
+///
+/// Field types only need to convert to and from the `mash` types, so
+/// `[f32; 3]` works as well as `mash::Vector` for positions.
+///
+/// The struct can also be built from `mash::Vector`, from
+/// `mash::load::Vertex` and from the vertex type of every enabled loader,
+/// with unmarked fields, and marked fields whose attribute the source
+/// vertex lacks, set to their defaults. It implements
+/// `TransformVertex`, so scenes of it can be flattened, and `HasLayout`,
+/// with the marked fields packed in order as 32-bit floats.
+#[proc_macro_derive(Vertex, attributes(mash))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// The vertex attributes that a field can hold.
+#[derive(Copy, Clone, PartialEq)]
+enum Attribute {
+    Position,
+    Normal,
+    TextureCoords,
+    Color,
+    Tangent,
+}
+
+const ATTRIBUTES: [(&str, Attribute); 5] = [
+    ("position", Attribute::Position),
+    ("normal", Attribute::Normal),
+    ("texture_coords", Attribute::TextureCoords),
+    ("color", Attribute::Color),
+    ("tangent", Attribute::Tangent),
+];
+
+/// The fields of the struct that hold each attribute.
+#[derive(Default)]
+struct Layout {
+    members: Vec<Member>,
+    position: Option<Member>,
+    normal: Option<Member>,
+    texture_coords: Option<Member>,
+    color: Option<Member>,
+    tangent: Option<Member>,
+}
+
+impl Layout {
+    fn slot(&mut self, attribute: Attribute) -> &mut Option<Member> {
+        match attribute {
+            Attribute::Position => &mut self.position,
+            Attribute::Normal => &mut self.normal,
+            Attribute::TextureCoords => &mut self.texture_coords,
+            Attribute::Color => &mut self.color,
+            Attribute::Tangent => &mut self.tangent,
+        }
+    }
+}
+
+fn layout(input: &DeriveInput) -> syn::Result<Layout> {
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(input, "only structs can be derived as vertices")),
+    };
+
+    let mut layout = Layout::default();
+    let named = matches!(fields, Fields::Named(..));
+    for (index, field) in fields.iter().enumerate() {
+        let member = if named {
+            Member::Named(field.ident.clone().unwrap())
+        } else {
+            Member::Unnamed(index.into())
+        };
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("mash")) {
+            attr.parse_nested_meta(|meta| {
+                let attribute = ATTRIBUTES.iter()
+                    .find(|&&(name, _)| meta.path.is_ident(name))
+                    .map(|&(_, attribute)| attribute)
+                    .ok_or_else(|| meta.error("expected one of `position`, `normal`, `texture_coords`, `color` or `tangent`"))?;
+
+                let slot = layout.slot(attribute);
+                if slot.is_some() {
+                    return Err(meta.error("only one field can hold each attribute"));
+                }
+                *slot = Some(member.clone());
+                Ok(())
+            })?;
+        }
+        layout.members.push(member);
+    }
+
+    if layout.position.is_none() {
+        return Err(syn::Error::new(Span::call_site(), "a vertex needs a field marked `#[mash(position)]`"));
+    }
+    Ok(layout)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let layout = layout(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let convert = quote!(::core::convert::From::from);
+
+    let position = layout.position.as_ref().unwrap();
+    let mut getters = Vec::new();
+    let mut traits = Vec::new();
+
+    if let Some(ref normal) = layout.normal {
+        getters.push(quote! {
+            fn normal(&self) -> ::core::option::Option<::mash::Vector> {
+                ::core::option::Option::Some(#convert(::core::clone::Clone::clone(&self.#normal)))
+            }
+        });
+        traits.push(quote! {
+            impl #impl_generics ::mash::HasNormal for #name #ty_generics #where_clause {
+                fn set_normal(&mut self, normal: ::mash::Vector) { self.#normal = #convert(normal); }
+            }
+        });
+    }
+
+    if let Some(ref texture_coords) = layout.texture_coords {
+        getters.push(quote! {
+            fn texture_coords(&self) -> ::core::option::Option<[f32; 2]> {
+                ::core::option::Option::Some(#convert(::core::clone::Clone::clone(&self.#texture_coords)))
+            }
+        });
+        traits.push(quote! {
+            impl #impl_generics ::mash::HasTexCoords for #name #ty_generics #where_clause {
+                fn set_texture_coords(&mut self, texture_coords: [f32; 2]) { self.#texture_coords = #convert(texture_coords); }
+            }
+        });
+    }
+
+    if let Some(ref color) = layout.color {
+        getters.push(quote! {
+            fn color(&self) -> ::core::option::Option<::mash::Color> {
+                ::core::option::Option::Some(#convert(::core::clone::Clone::clone(&self.#color)))
+            }
+        });
+        traits.push(quote! {
+            impl #impl_generics ::mash::HasColor for #name #ty_generics #where_clause {
+                fn set_color(&mut self, color: ::mash::Color) { self.#color = #convert(color); }
+            }
+        });
+    }
+
+    if let Some(ref tangent) = layout.tangent {
+        getters.push(quote! {
+            fn tangent(&self) -> ::core::option::Option<[f32; 4]> {
+                ::core::option::Option::Some(#convert(::core::clone::Clone::clone(&self.#tangent)))
+            }
+        });
+        traits.push(quote! {
+            impl #impl_generics ::mash::HasTangent for #name #ty_generics #where_clause {
+                fn set_tangent(&mut self, tangent: [f32; 4]) { self.#tangent = #convert(tangent); }
+            }
+        });
+    }
+
+    let layout_attributes = layout.members.iter().filter_map(|member| {
+        let (semantic, components) = if Some(member) == layout.position.as_ref() {
+            (quote!(Position), 3usize)
+        } else if Some(member) == layout.normal.as_ref() {
+            (quote!(Normal), 3)
+        } else if Some(member) == layout.texture_coords.as_ref() {
+            (quote!(TexCoords), 2)
+        } else if Some(member) == layout.color.as_ref() {
+            (quote!(Color), 3)
+        } else if Some(member) == layout.tangent.as_ref() {
+            (quote!(Tangent), 4)
+        } else {
+            return None;
+        };
+        Some(quote!(.attribute(::mash::layout::Semantic::#semantic, ::mash::layout::ComponentType::Float32, #components)))
+    });
+
+    // Builds the struct from a `mash::load::Vertex` named `v`, leaving
+    // attributes that it does not have at their defaults.
+    let initializers = layout.members.iter().map(|member| {
+        let value = if Some(member) == layout.position.as_ref() {
+            quote!(#convert(v.position))
+        } else if Some(member) == layout.normal.as_ref() {
+            quote!(v.normal.map(#convert).unwrap_or_default())
+        } else if Some(member) == layout.texture_coords.as_ref() {
+            quote!(v.texture_coords.map(#convert).unwrap_or_default())
+        } else if Some(member) == layout.color.as_ref() {
+            quote!(v.color.map(#convert).unwrap_or_default())
+        } else if Some(member) == layout.tangent.as_ref() {
+            quote!(v.tangent.map(#convert).unwrap_or_default())
+        } else {
+            quote!(::core::default::Default::default())
+        };
+        quote!(#member: #value)
+    });
+
+    let transform_normal = layout.normal.as_ref().map(|normal| quote! {
+        vertex.#normal = #convert(matrix.transform_normal(#convert(::core::clone::Clone::clone(&self.#normal))));
+    });
+    let transform_tangent = layout.tangent.as_ref().map(|tangent| quote! {
+        let t: [f32; 4] = #convert(::core::clone::Clone::clone(&self.#tangent));
+        let direction = matrix.transform_vector(::mash::Vector(t[0], t[1], t[2])).normalize();
+        // Mirroring flips the bitangent relative to the normal and tangent.
+        let w = if matrix.determinant() < 0.0 { -t[3] } else { t[3] };
+        vertex.#tangent = #convert([direction.0, direction.1, direction.2, w]);
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::mash::Vertex for #name #ty_generics #where_clause {
+            fn position(&self) -> ::mash::Vector { #convert(::core::clone::Clone::clone(&self.#position)) }
+            #(#getters)*
+        }
+
+        #(#traits)*
+
+        impl #impl_generics ::mash::TransformVertex for #name #ty_generics #where_clause {
+            fn transform(&self, matrix: &::mash::Matrix4) -> Self {
+                let mut vertex = ::core::clone::Clone::clone(self);
+                vertex.#position = #convert(matrix.transform_point(#convert(::core::clone::Clone::clone(&self.#position))));
+                #transform_normal
+                #transform_tangent
+                vertex
+            }
+        }
+
+        impl #impl_generics ::mash::HasLayout for #name #ty_generics #where_clause {
+            fn layout() -> ::mash::VertexLayout {
+                ::mash::VertexLayout::new() #(#layout_attributes)*
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<::mash::load::Vertex> for #name #ty_generics #where_clause {
+            fn from(v: ::mash::load::Vertex) -> Self {
+                #name { #(#initializers),* }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<::mash::Vector> for #name #ty_generics #where_clause {
+            fn from(position: ::mash::Vector) -> Self {
+                #convert(<::mash::load::Vertex as ::core::convert::From<::mash::Vector>>::from(position))
+            }
+        }
+
+        ::mash::__impl_from_format_vertices! {
+            [#impl_generics] [#name #ty_generics] [#where_clause]
+        }
+    })
+}
@@ -0,0 +1,114 @@
+use mash::layout::{self, ComponentType, Semantic};
+use mash::{build, load, HasColor, HasNormal, HasTexCoords, Matrix4, Model, Scene, TransformVertex, Vector, Vertex, VertexLayout};
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Vertex)]
+struct Textured {
+    #[mash(position)]
+    position: [f32; 3],
+    #[mash(normal)]
+    normal: Vector,
+    #[mash(texture_coords)]
+    uv: [f32; 2],
+    #[mash(color)]
+    color: [f32; 3],
+    material: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Vertex)]
+struct Mapped {
+    #[mash(position)]
+    position: Vector,
+    #[mash(normal)]
+    normal: Vector,
+    #[mash(tangent)]
+    tangent: [f32; 4],
+}
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Vertex)]
+struct Positioned(#[mash(position)] Vector, u8);
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Vertex)]
+struct Tagged<T: Clone + std::fmt::Debug + PartialEq + PartialOrd + Default> {
+    #[mash(position)]
+    position: Vector,
+    tag: T,
+}
+
+#[test]
+fn implements_vertex_and_attribute_traits() {
+    let mut vertex = Textured {
+        position: [1.0, 2.0, 3.0],
+        normal: Vector(0.0, 1.0, 0.0),
+        uv: [0.5, 0.25],
+        color: [1.0, 0.0, 0.0],
+        material: 7,
+    };
+
+    assert_eq!(vertex.position(), Vector(1.0, 2.0, 3.0));
+    assert_eq!(mash::Vertex::normal(&vertex), Some(Vector(0.0, 1.0, 0.0)));
+    assert_eq!(mash::Vertex::texture_coords(&vertex), Some([0.5, 0.25]));
+    assert_eq!(mash::Vertex::tangent(&vertex), None);
+
+    vertex.set_normal(Vector(0.0, 0.0, 1.0));
+    vertex.set_texture_coords([1.0, 1.0]);
+    vertex.set_color(mash::Color(0.0, 0.0, 1.0));
+    assert_eq!(vertex.normal, Vector(0.0, 0.0, 1.0));
+    assert_eq!(vertex.uv, [1.0, 1.0]);
+    assert_eq!(vertex.color, [0.0, 0.0, 1.0]);
+    assert_eq!(vertex.material, 7);
+}
+
+#[test]
+fn loads_every_format() {
+    let model: Model<Textured, u32> = Model::new(load::wavefront::from_path("../res/crate.obj").unwrap()).unwrap();
+    assert!(model.mesh.vertices.iter().any(|v| v.uv == [1.0, 1.0]));
+    assert!(model.mesh.vertices.iter().all(|v| v.normal.length() == 1.0));
+    assert!(model.mesh.vertices.iter().all(|v| v.material == 0));
+
+    // STL files only have normals, so the other attributes are left at their defaults.
+    let model: Model<Textured, u32> = Model::new(load::from_path("../res/cube-binary.stl").unwrap()).unwrap();
+    assert!(model.mesh.vertices.iter().all(|v| v.normal.length() == 1.0 && v.uv == [0.0, 0.0] && v.color == [0.0, 0.0, 0.0]));
+
+    for path in &["../res/cube.obj", "../res/cube-binary.stl", "../res/cube.ply", "../res/cube.gltf"] {
+        let model: Model<Positioned, u32> = Model::new(load::from_path(path).unwrap()).unwrap();
+        assert!(!model.mesh.indices.is_empty(), "{}", path);
+    }
+}
+
+#[test]
+fn works_with_builders_and_scenes() {
+    let cube: mash::TriangularMesh<Textured, u16> = build::cube_with_normals(1.0);
+    assert_eq!(cube.vertices[0].normal, Vector(1.0, 0.0, 0.0));
+
+    let moved = cube.vertices[0].transform(&Matrix4::from_translation(Vector(0.0, 0.0, 5.0)));
+    assert_eq!(moved.position, [1.0, -1.0, 4.0]);
+    assert_eq!(moved.normal, Vector(1.0, 0.0, 0.0));
+
+    let scene: Scene<Tagged<i32>, u16> = Scene::from_model(Model::from_mesh(build::unit_cube()));
+    assert_eq!(scene.flatten().unwrap().mesh.vertices[0].tag, 0);
+}
+
+#[test]
+fn describes_the_marked_fields_as_a_layout() {
+    let layout = VertexLayout::of::<Textured>();
+    assert_eq!(layout.attributes.iter().map(|a| (a.semantic, a.offset)).collect::<Vec<_>>(), vec![
+        (Semantic::Position, 0), (Semantic::Normal, 12), (Semantic::TexCoords, 24), (Semantic::Color, 32),
+    ]);
+    assert!(layout.attributes.iter().all(|a| a.component_type == ComponentType::Float32));
+    assert_eq!(layout.stride, 44);
+
+    let cube: mash::TriangularMesh<Textured, u16> = build::cube_with_normals(1.0);
+    let bytes = layout::interleave(&cube, &layout).unwrap();
+    assert_eq!(bytes.len(), 24 * 44);
+    assert_eq!(&bytes[12..16], &1.0f32.to_le_bytes());
+}
+
+#[test]
+fn flips_bitangent_sign_when_mirrored() {
+    let vertex = Mapped { position: Vector(1.0, 2.0, 0.0), normal: Vector(0.0, 0.0, 1.0), tangent: [1.0, 0.0, 0.0, 1.0] };
+
+    let mirrored = vertex.transform(&Matrix4::from_scale(Vector(-1.0, 1.0, 1.0)));
+    assert_eq!(mirrored.tangent, [-1.0, 0.0, 0.0, -1.0]);
+    let moved = vertex.transform(&Matrix4::from_translation(Vector(0.0, 0.0, 5.0)));
+    assert_eq!(moved.tangent, [1.0, 0.0, 0.0, 1.0]);
+}
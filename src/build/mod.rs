@@ -1,6 +1,6 @@
 //! Mesh builders.
 
-use {TriangularMesh, Vertex, Index, Vector};
+use {TriangularMesh, Vertex, HasNormal, Index, Vector};
 
 /// Creates a cube with a side length of one.
 pub fn unit_cube<V,I>() -> TriangularMesh<V,I>
@@ -41,8 +41,42 @@ pub fn cube<V,I>(scale: f32) -> TriangularMesh<V,I>
     let indices = indices.unwrap();
 
     TriangularMesh {
-        vertices: vertices,
-        indices: indices,
+        vertices,
+        indices,
+    }
+}
+
+/// Create a cube with separate vertices for each face, so that every
+/// vertex can hold the normal of its face.
+///
+/// * `scale` has the same meaning as in `cube`.
+pub fn cube_with_normals<V,I>(scale: f32) -> TriangularMesh<V,I>
+    where V: HasNormal, I: Index, V: From<Vector> {
+    // Each face is given by its normal and two edge directions whose cross product is the normal.
+    let faces = [
+        (Vector(1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0), Vector(0.0, 0.0, 1.0)),
+        (Vector(-1.0, 0.0, 0.0), Vector(0.0, 0.0, 1.0), Vector(0.0, 1.0, 0.0)),
+        (Vector(0.0, 1.0, 0.0), Vector(0.0, 0.0, 1.0), Vector(1.0, 0.0, 0.0)),
+        (Vector(0.0, -1.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 0.0, 1.0)),
+        (Vector(0.0, 0.0, 1.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0)),
+        (Vector(0.0, 0.0, -1.0), Vector(0.0, 1.0, 0.0), Vector(1.0, 0.0, 0.0)),
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for &(normal, u, v) in faces.iter() {
+        let first = vertices.len() as u64;
+        for &(a, b) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter() {
+            let mut vertex = V::from((normal + u * a + v * b) * scale);
+            vertex.set_normal(normal);
+            vertices.push(vertex);
+        }
+        indices.extend([0, 1, 2, 0, 2, 3].iter().map(|&i| I::from_u64(first + i).unwrap()));
+    }
+
+    TriangularMesh {
+        vertices,
+        indices,
     }
 }
 
@@ -55,5 +89,18 @@ mod test {
         let cube: TriangularMesh<Vector, u64> = unit_cube();
         assert_eq!(cube.triangles().count(), 12);
     }
+
+    #[test]
+    fn cube_normals_face_outwards() {
+        let cube: TriangularMesh<::load::Vertex, u16> = cube_with_normals(2.0);
+        assert_eq!(cube.vertices.len(), 24);
+
+        for triangle in cube.triangles() {
+            let [a, b, c] = triangle.vertices;
+            let winding = (b.position - a.position).cross(c.position - a.position).normalize();
+            assert_eq!(winding, a.normal.unwrap());
+            assert_eq!(a.position.dot(a.normal.unwrap()), 2.0);
+        }
+    }
 }
 
@@ -0,0 +1,98 @@
+//! Support code for `#[derive(Vertex)]`.
+//!
+//! The derive macro cannot see which loaders are enabled, as it expands in
+//! the user's crate, so it defers the per-format `From` impls to these
+//! macros, whose bodies depend on the features `mash` was built with.
+
+/// Implements `From` for the vertex type of every enabled loader, by way of
+/// `load::Vertex`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertices {
+    ([$($generics:tt)*] [$($ty:tt)*] [$($where:tt)*]) => {
+        $crate::__impl_from_format_vertex_wavefront! { [$($generics)*] [$($ty)*] [$($where)*] }
+        $crate::__impl_from_format_vertex_stl! { [$($generics)*] [$($ty)*] [$($where)*] }
+        $crate::__impl_from_format_vertex_ply! { [$($generics)*] [$($ty)*] [$($where)*] }
+        $crate::__impl_from_format_vertex_gltf! { [$($generics)*] [$($ty)*] [$($where)*] }
+    }
+}
+
+#[cfg(feature = "wavefront")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_wavefront {
+    ([$($generics:tt)*] [$($ty:tt)*] [$($where:tt)*]) => {
+        impl $($generics)* ::core::convert::From<$crate::load::wavefront::Vertex> for $($ty)* $($where)* {
+            fn from(v: $crate::load::wavefront::Vertex) -> Self {
+                ::core::convert::From::from($crate::load::Vertex::from(v))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "wavefront"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_wavefront {
+    ($($tokens:tt)*) => {}
+}
+
+#[cfg(feature = "stl")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_stl {
+    ([$($generics:tt)*] [$($ty:tt)*] [$($where:tt)*]) => {
+        impl $($generics)* ::core::convert::From<$crate::load::stl::Vertex> for $($ty)* $($where)* {
+            fn from(v: $crate::load::stl::Vertex) -> Self {
+                ::core::convert::From::from($crate::load::Vertex::from(v))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "stl"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_stl {
+    ($($tokens:tt)*) => {}
+}
+
+#[cfg(feature = "ply")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_ply {
+    ([$($generics:tt)*] [$($ty:tt)*] [$($where:tt)*]) => {
+        impl $($generics)* ::core::convert::From<$crate::load::ply::Vertex> for $($ty)* $($where)* {
+            fn from(v: $crate::load::ply::Vertex) -> Self {
+                ::core::convert::From::from($crate::load::Vertex::from(v))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "ply"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_ply {
+    ($($tokens:tt)*) => {}
+}
+
+#[cfg(feature = "gltf")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_gltf {
+    ([$($generics:tt)*] [$($ty:tt)*] [$($where:tt)*]) => {
+        impl $($generics)* ::core::convert::From<$crate::load::gltf::Vertex> for $($ty)* $($where)* {
+            fn from(v: $crate::load::gltf::Vertex) -> Self {
+                ::core::convert::From::from($crate::load::Vertex::from(v))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "gltf"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_from_format_vertex_gltf {
+    ($($tokens:tt)*) => {}
+}
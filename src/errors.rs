@@ -7,7 +7,7 @@ error_chain! {
 
     foreign_links {
         Io(::std::io::Error);
-        WavefrontLoadError(::tobj::LoadError) #[cfg(feature = "wavefront")];
+        GltfLoadError(::gltf::Error) #[cfg(feature = "gltf")];
     }
 
     errors {
@@ -16,11 +16,58 @@ error_chain! {
             display("unknown model format: {}", reason)
         }
 
+        FormatNotEnabled(format: String, feature: String) {
+            description("model format support not enabled")
+            display("support for {} files is not enabled: enable the '{}' cargo feature of mash",
+                    format, feature)
+        }
+
         IndexTooSmall(index: u64, bits_available: u8) {
             description("index too small for mesh")
             display("index too small for mesh: index '{}' cannot fit in {}-bits",
                     index, bits_available)
         }
+
+        TooManyTriangles(count: u64, bits_available: u8) {
+            description("too many triangles for the file format")
+            display("too many triangles for the file format: {} triangles cannot be counted in {}-bits",
+                    count, bits_available)
+        }
+
+        WavefrontParseError(file: String, line: usize, column: usize, reason: String) {
+            description("invalid Wavefront file")
+            display("{}:{}:{}: {}", file, line, column, reason)
+        }
+
+        InvalidStl(reason: String) {
+            description("invalid STL file")
+            display("invalid STL file: {}", reason)
+        }
+
+        InvalidPly(reason: String) {
+            description("invalid PLY file")
+            display("invalid PLY file: {}", reason)
+        }
+
+        InvalidGltf(reason: String) {
+            description("invalid glTF file")
+            display("invalid glTF file: {}", reason)
+        }
+
+        InvalidScene(reason: String) {
+            description("invalid scene")
+            display("invalid scene: {}", reason)
+        }
+
+        MissingAttribute(attribute: String, vertex: usize) {
+            description("vertex is missing an attribute")
+            display("vertex {} has no {}", vertex, attribute)
+        }
+
+        InvalidLayout(reason: String) {
+            description("invalid vertex layout")
+            display("invalid vertex layout: {}", reason)
+        }
     }
 }
 
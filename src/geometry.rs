@@ -1,19 +1,87 @@
 //! Geometric type definitions.
 
 use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg};
 
 /// A 3-dimensional vector.
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
 pub struct Vector(pub f32, pub f32, pub f32);
 
 /// A color.
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
 pub struct Color(pub f32, pub f32, pub f32);
 
+/// An axis-aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+    /// The corner with the smallest coordinates.
+    pub min: Vector,
+    /// The corner with the largest coordinates.
+    pub max: Vector,
+}
+
+/// A 4x4 transformation matrix, stored as an array of columns.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix4(pub [[f32; 4]; 4]);
+
+/// A rotation, stored as `(x, y, z, w)` where `w` is the scalar part.
+///
+/// Rotations are expected to be unit quaternions.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quaternion(pub f32, pub f32, pub f32, pub f32);
+
 /// A vertex.
 pub trait Vertex : Clone + Debug + PartialEq + PartialOrd {
     /// Get the position of the vertex.
     fn position(&self) -> Vector;
+
+    /// Get the normal of the vertex, if it has one.
+    fn normal(&self) -> Option<Vector> { None }
+
+    /// Get the texture coordinates of the vertex, if it has any.
+    fn texture_coords(&self) -> Option<[f32; 2]> { None }
+
+    /// Get the color of the vertex, if it has one.
+    fn color(&self) -> Option<Color> { None }
+
+    /// Get the tangent of the vertex, if it has one, with the sign of the
+    /// bitangent stored in the last component.
+    fn tangent(&self) -> Option<[f32; 4]> { None }
+}
+
+/// A vertex that stores a normal.
+///
+/// The normal is read with `Vertex::normal`, which should return `Some`
+/// of the normal that was set.
+pub trait HasNormal : Vertex {
+    /// Set the normal of the vertex.
+    fn set_normal(&mut self, normal: Vector);
+}
+
+/// A vertex that stores texture coordinates.
+///
+/// `Vertex::texture_coords` should return `Some` of the coordinates that
+/// were set.
+pub trait HasTexCoords : Vertex {
+    /// Set the texture coordinates of the vertex.
+    fn set_texture_coords(&mut self, texture_coords: [f32; 2]);
+}
+
+/// A vertex that stores a color.
+///
+/// `Vertex::color` should return `Some` of the color that was set.
+pub trait HasColor : Vertex {
+    /// Set the color of the vertex.
+    fn set_color(&mut self, color: Color);
+}
+
+/// A vertex that stores a tangent.
+///
+/// `Vertex::tangent` should return `Some` of the tangent that was set.
+pub trait HasTangent : Vertex {
+    /// Set the tangent of the vertex, with the sign of the bitangent
+    /// stored in the last component.
+    fn set_tangent(&mut self, tangent: [f32; 4]);
 }
 
 /// A triangle.
@@ -23,8 +91,476 @@ pub struct Triangle<V: Vertex> {
     pub vertices: [V; 3],
 }
 
+impl Vector {
+    /// The vector with every component set to zero.
+    pub const ZERO: Vector = Vector(0.0, 0.0, 0.0);
+
+    /// Gets the dot product of two vectors.
+    pub fn dot(self, other: Vector) -> f32 {
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2
+    }
+
+    /// Gets the cross product of two vectors.
+    pub fn cross(self, other: Vector) -> Vector {
+        Vector(self.1 * other.2 - self.2 * other.1,
+               self.2 * other.0 - self.0 * other.2,
+               self.0 * other.1 - self.1 * other.0)
+    }
+
+    /// Gets the length of the vector.
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    /// Gets the squared length of the vector, which is cheaper than the length.
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    /// Gets the vector scaled to unit length.
+    ///
+    /// The zero vector has no direction, and is returned unchanged.
+    pub fn normalize(self) -> Vector {
+        let length = self.length();
+        if length > 0.0 { self / length } else { self }
+    }
+
+    /// Interpolates linearly between two vectors, giving `self` at `t = 0`
+    /// and `other` at `t = 1`.
+    pub fn lerp(self, other: Vector, t: f32) -> Vector {
+        self + (other - self) * t
+    }
+
+    /// Gets the smallest of each component of two vectors.
+    pub fn min(self, other: Vector) -> Vector {
+        Vector(self.0.min(other.0), self.1.min(other.1), self.2.min(other.2))
+    }
+
+    /// Gets the largest of each component of two vectors.
+    pub fn max(self, other: Vector) -> Vector {
+        Vector(self.0.max(other.0), self.1.max(other.1), self.2.max(other.2))
+    }
+}
+
+impl From<[f32; 3]> for Vector {
+    fn from(v: [f32; 3]) -> Vector { Vector(v[0], v[1], v[2]) }
+}
+
+impl From<Vector> for [f32; 3] {
+    fn from(v: Vector) -> Self { [v.0, v.1, v.2] }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from(c: [f32; 3]) -> Color { Color(c[0], c[1], c[2]) }
+}
+
+impl From<Color> for [f32; 3] {
+    fn from(c: Color) -> Self { [c.0, c.1, c.2] }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+    fn add(self, other: Vector) -> Vector { Vector(self.0 + other.0, self.1 + other.1, self.2 + other.2) }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+    fn sub(self, other: Vector) -> Vector { Vector(self.0 - other.0, self.1 - other.1, self.2 - other.2) }
+}
+
+impl Mul<f32> for Vector {
+    type Output = Vector;
+    fn mul(self, scale: f32) -> Vector { Vector(self.0 * scale, self.1 * scale, self.2 * scale) }
+}
+
+impl Mul<Vector> for f32 {
+    type Output = Vector;
+    fn mul(self, v: Vector) -> Vector { v * self }
+}
+
+impl Div<f32> for Vector {
+    type Output = Vector;
+    fn div(self, scale: f32) -> Vector { Vector(self.0 / scale, self.1 / scale, self.2 / scale) }
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+    fn neg(self) -> Vector { Vector(-self.0, -self.1, -self.2) }
+}
+
+impl AddAssign for Vector {
+    fn add_assign(&mut self, other: Vector) { *self = *self + other; }
+}
+
+impl SubAssign for Vector {
+    fn sub_assign(&mut self, other: Vector) { *self = *self - other; }
+}
+
+impl MulAssign<f32> for Vector {
+    fn mul_assign(&mut self, scale: f32) { *self = *self * scale; }
+}
+
+impl DivAssign<f32> for Vector {
+    fn div_assign(&mut self, scale: f32) { *self = *self / scale; }
+}
+
+impl Bounds {
+    /// Gets the smallest box containing all of the points, if there are any.
+    pub fn from_points<I>(points: I) -> Option<Self>
+        where I: IntoIterator<Item=Vector> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        Some(points.fold(Bounds { min: first, max: first }, |bounds, p| Bounds {
+            min: bounds.min.min(p),
+            max: bounds.max.max(p),
+        }))
+    }
+}
+
+impl Matrix4 {
+    /// The matrix that leaves everything unchanged.
+    pub const IDENTITY: Matrix4 = Matrix4([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    /// Creates a matrix that moves points by an offset.
+    pub fn from_translation(offset: Vector) -> Matrix4 {
+        let mut matrix = Matrix4::IDENTITY;
+        matrix.0[3] = [offset.0, offset.1, offset.2, 1.0];
+        matrix
+    }
+
+    /// Creates a matrix that scales along each axis.
+    pub fn from_scale(scale: Vector) -> Matrix4 {
+        let mut matrix = Matrix4::IDENTITY;
+        matrix.0[0][0] = scale.0;
+        matrix.0[1][1] = scale.1;
+        matrix.0[2][2] = scale.2;
+        matrix
+    }
+
+    /// Creates a matrix that applies a rotation.
+    pub fn from_rotation(rotation: Quaternion) -> Matrix4 {
+        let Quaternion(x, y, z, w) = rotation;
+        Matrix4([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + w * z), 2.0 * (x * z - w * y), 0.0],
+            [2.0 * (x * y - w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + w * x), 0.0],
+            [2.0 * (x * z + w * y), 2.0 * (y * z - w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Creates a matrix that scales, then rotates, then translates, as
+    /// glTF nodes do.
+    pub fn from_translation_rotation_scale(translation: Vector, rotation: Quaternion, scale: Vector) -> Matrix4 {
+        let mut matrix = Matrix4::from_rotation(rotation);
+        for (column, s) in matrix.0.iter_mut().zip(&[scale.0, scale.1, scale.2]) {
+            for cell in column.iter_mut().take(3) {
+                *cell *= s;
+            }
+        }
+        matrix.0[3] = [translation.0, translation.1, translation.2, 1.0];
+        matrix
+    }
+
+    /// Gets the matrix that applies `other` and then this matrix.
+    pub fn multiply(&self, other: &Matrix4) -> Matrix4 {
+        let (a, b) = (&self.0, &other.0);
+        let mut result = [[0.0; 4]; 4];
+        for (col, column) in result.iter_mut().enumerate() {
+            for (row, cell) in column.iter_mut().enumerate() {
+                *cell = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+            }
+        }
+        Matrix4(result)
+    }
+
+    /// Gets the matrix with its rows and columns swapped.
+    pub fn transpose(&self) -> Matrix4 {
+        let mut result = [[0.0; 4]; 4];
+        for (col, column) in result.iter_mut().enumerate() {
+            for (row, cell) in column.iter_mut().enumerate() {
+                *cell = self.0[row][col];
+            }
+        }
+        Matrix4(result)
+    }
+
+    /// Gets the determinant of the matrix.
+    pub fn determinant(&self) -> f32 {
+        let m = &self.0;
+        // Laplace expansion along the last row, using 3x3 minors.
+        let minor = |skip: usize| {
+            let cols: Vec<usize> = (0..4).filter(|&c| c != skip).collect();
+            let c = |a: usize, b: usize, x: usize, y: usize| m[cols[a]][x] * m[cols[b]][y] - m[cols[a]][y] * m[cols[b]][x];
+            m[cols[0]][0] * c(1, 2, 1, 2) - m[cols[1]][0] * c(0, 2, 1, 2) + m[cols[2]][0] * c(0, 1, 1, 2)
+        };
+        (0..4).map(|col| {
+            let sign = if (col + 3) % 2 == 0 { 1.0 } else { -1.0 };
+            sign * m[col][3] * minor(col)
+        }).sum()
+    }
+
+    /// Gets the matrix that undoes this one, if the matrix is invertible.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        // Gauss-Jordan elimination with partial pivoting, on rows.
+        let mut a = self.transpose().0;
+        let mut inverse = Matrix4::IDENTITY.0;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col] == 0.0 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inverse.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inverse[col][k] *= scale;
+            }
+
+            for row in (0..4).filter(|&row| row != col) {
+                let factor = a[row][col];
+                for k in 0..4 {
+                    a[row][k] -= factor * a[col][k];
+                    inverse[row][k] -= factor * inverse[col][k];
+                }
+            }
+        }
+
+        Some(Matrix4(inverse).transpose())
+    }
+
+    /// Transforms a point, including translation.
+    pub fn transform_point(&self, p: Vector) -> Vector {
+        self.apply(p, 1.0)
+    }
+
+    /// Transforms a direction, ignoring translation.
+    pub fn transform_vector(&self, v: Vector) -> Vector {
+        self.apply(v, 0.0)
+    }
+
+    /// Transforms a surface normal, keeping it perpendicular to the
+    /// transformed surface, and normalizes the result.
+    pub fn transform_normal(&self, n: Vector) -> Vector {
+        self.cofactors().apply(n, 0.0).normalize()
+    }
+
+    fn apply(&self, v: Vector, w: f32) -> Vector {
+        let m = &self.0;
+        let row = |i: usize| m[0][i] * v.0 + m[1][i] * v.1 + m[2][i] * v.2 + m[3][i] * w;
+        Vector(row(0), row(1), row(2))
+    }
+
+    /// Gets the cofactor matrix of the upper 3x3 part of the matrix.
+    ///
+    /// This is the inverse transpose scaled by the determinant, which is
+    /// all that is needed to transform normals that are renormalized after.
+    fn cofactors(&self) -> Matrix4 {
+        let m = &self.0;
+        let c = |a: usize, b: usize, x: usize, y: usize| m[a][x] * m[b][y] - m[a][y] * m[b][x];
+        let det = m[0][0] * c(1, 2, 1, 2) - m[1][0] * c(0, 2, 1, 2) + m[2][0] * c(0, 1, 1, 2);
+        let sign = if det < 0.0 { -1.0 } else { 1.0 };
+
+        let mut result = Matrix4::IDENTITY.0;
+        for (col, column) in result.iter_mut().enumerate().take(3) {
+            for (row, cell) in column.iter_mut().enumerate().take(3) {
+                let (a, b) = match col { 0 => (1, 2), 1 => (0, 2), _ => (0, 1) };
+                let (x, y) = match row { 0 => (1, 2), 1 => (0, 2), _ => (0, 1) };
+                let parity = if (col + row) % 2 == 0 { 1.0 } else { -1.0 };
+                *cell = sign * parity * c(a, b, x, y);
+            }
+        }
+        Matrix4(result)
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+    fn mul(self, other: Matrix4) -> Matrix4 { self.multiply(&other) }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Self { Matrix4::IDENTITY }
+}
+
+impl Quaternion {
+    /// The rotation that leaves everything unchanged.
+    pub const IDENTITY: Quaternion = Quaternion(0.0, 0.0, 0.0, 1.0);
+
+    /// Creates a rotation by an angle in radians around an axis.
+    pub fn from_axis_angle(axis: Vector, angle: f32) -> Quaternion {
+        let Vector(x, y, z) = axis.normalize() * (angle * 0.5).sin();
+        Quaternion(x, y, z, (angle * 0.5).cos())
+    }
+
+    /// Gets the dot product of two quaternions.
+    pub fn dot(self, other: Quaternion) -> f32 {
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2 + self.3 * other.3
+    }
+
+    /// Gets the length of the quaternion.
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Gets the quaternion scaled to unit length.
+    ///
+    /// The zero quaternion is returned unchanged.
+    pub fn normalize(self) -> Quaternion {
+        let length = self.length();
+        if length > 0.0 { Quaternion(self.0 / length, self.1 / length, self.2 / length, self.3 / length) } else { self }
+    }
+
+    /// Gets the quaternion with its vector part negated, which is the
+    /// inverse of a unit quaternion.
+    pub fn conjugate(self) -> Quaternion {
+        Quaternion(-self.0, -self.1, -self.2, self.3)
+    }
+
+    /// Gets the rotation that undoes this one, if the quaternion is not zero.
+    pub fn inverse(self) -> Option<Quaternion> {
+        let length_squared = self.dot(self);
+        if length_squared == 0.0 {
+            return None;
+        }
+        let Quaternion(x, y, z, w) = self.conjugate();
+        Some(Quaternion(x / length_squared, y / length_squared, z / length_squared, w / length_squared))
+    }
+
+    /// Gets the rotation that applies `other` and then this rotation.
+    pub fn multiply(&self, other: &Quaternion) -> Quaternion {
+        let (Quaternion(ax, ay, az, aw), Quaternion(bx, by, bz, bw)) = (*self, *other);
+        Quaternion(aw * bx + ax * bw + ay * bz - az * by,
+                   aw * by - ax * bz + ay * bw + az * bx,
+                   aw * bz + ax * by - ay * bx + az * bw,
+                   aw * bw - ax * bx - ay * by - az * bz)
+    }
+
+    /// Rotates a vector.
+    pub fn rotate(&self, v: Vector) -> Vector {
+        let axis = Vector(self.0, self.1, self.2);
+        let t = axis.cross(v) * 2.0;
+        v + t * self.3 + axis.cross(t)
+    }
+
+    /// Interpolates along the shortest arc between two rotations, giving
+    /// `self` at `t = 0` and `other` at `t = 1`.
+    pub fn slerp(self, other: Quaternion, t: f32) -> Quaternion {
+        let mut cos = self.dot(other);
+        let mut other = other;
+        if cos < 0.0 {
+            cos = -cos;
+            other = Quaternion(-other.0, -other.1, -other.2, -other.3);
+        }
+
+        // Nearly identical rotations would divide by almost zero.
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+
+        Quaternion(a * self.0 + b * other.0, a * self.1 + b * other.1,
+                   a * self.2 + b * other.2, a * self.3 + b * other.3).normalize()
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+    fn mul(self, other: Quaternion) -> Quaternion { self.multiply(&other) }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self { Quaternion::IDENTITY }
+}
+
 // Allow (x,y,z) pairs to work as vectors.
 impl Vertex for Vector {
     fn position(&self) -> Vector { *self }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_near(a: Vector, b: Vector) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_matrix_near(a: &Matrix4, b: &Matrix4) {
+        for (x, y) in a.0.iter().flat_map(|c| c.iter()).zip(b.0.iter().flat_map(|c| c.iter())) {
+            assert!((x - y).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn vector_arithmetic() {
+        let (a, b) = (Vector(1.0, 2.0, 3.0), Vector(4.0, 5.0, 6.0));
+        assert_eq!(a + b, Vector(5.0, 7.0, 9.0));
+        assert_eq!(b - a, Vector(3.0, 3.0, 3.0));
+        assert_eq!(a * 2.0, 2.0 * a);
+        assert_eq!(-a / 2.0, Vector(-0.5, -1.0, -1.5));
+        assert_eq!(a.dot(b), 32.0);
+        assert_eq!(Vector(1.0, 0.0, 0.0).cross(Vector(0.0, 1.0, 0.0)), Vector(0.0, 0.0, 1.0));
+        assert_eq!(Vector(3.0, 0.0, 4.0).length(), 5.0);
+        assert_eq!(Vector(0.0, 0.0, 2.0).normalize(), Vector(0.0, 0.0, 1.0));
+        assert_eq!(Vector::ZERO.normalize(), Vector::ZERO);
+        assert_eq!(a.lerp(b, 0.5), Vector(2.5, 3.5, 4.5));
+
+        let mut c = a;
+        c += b;
+        c *= 2.0;
+        assert_eq!(c, Vector(10.0, 14.0, 18.0));
+    }
+
+    #[test]
+    fn rotates_vectors() {
+        let rotation = Quaternion::from_axis_angle(Vector(0.0, 0.0, 1.0), FRAC_PI_2);
+        assert_near(rotation.rotate(Vector(1.0, 0.0, 0.0)), Vector(0.0, 1.0, 0.0));
+        assert_near(Matrix4::from_rotation(rotation).transform_point(Vector(1.0, 0.0, 0.0)), Vector(0.0, 1.0, 0.0));
+
+        let twice = rotation * rotation;
+        assert_near(twice.rotate(Vector(1.0, 0.0, 0.0)), Vector(-1.0, 0.0, 0.0));
+        assert_near((twice * rotation.inverse().unwrap()).rotate(Vector(1.0, 0.0, 0.0)), Vector(0.0, 1.0, 0.0));
+        assert_near(Quaternion::IDENTITY.slerp(twice, 0.5).rotate(Vector(1.0, 0.0, 0.0)), Vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn composes_and_inverts_matrices() {
+        let rotation = Quaternion::from_axis_angle(Vector(1.0, 1.0, 0.0), 0.7);
+        let matrix = Matrix4::from_translation_rotation_scale(Vector(1.0, 2.0, 3.0), rotation, Vector(2.0, 2.0, 2.0));
+        let composed = Matrix4::from_translation(Vector(1.0, 2.0, 3.0))
+            * Matrix4::from_rotation(rotation)
+            * Matrix4::from_scale(Vector(2.0, 2.0, 2.0));
+        assert_matrix_near(&matrix, &composed);
+        assert!((matrix.determinant() - 8.0).abs() < 1e-4);
+
+        let inverse = matrix.inverse().unwrap();
+        assert_matrix_near(&(matrix * inverse), &Matrix4::IDENTITY);
+        let p = Vector(-3.0, 0.5, 7.0);
+        assert_near(inverse.transform_point(matrix.transform_point(p)), p);
+
+        assert_eq!(Matrix4::from_scale(Vector(1.0, 0.0, 1.0)).inverse(), None);
+    }
+
+    #[test]
+    fn transforms_normals_with_inverse_transpose() {
+        let matrix = Matrix4::from_scale(Vector(2.0, 1.0, 1.0));
+        // The normal of the plane x = y is squashed towards x, not stretched.
+        let normal = matrix.transform_normal(Vector(1.0, -1.0, 0.0).normalize());
+        assert_near(normal, Vector(0.5, -1.0, 0.0).normalize());
+        assert_near(matrix.transform_vector(Vector(1.0, 1.0, 0.0)), Vector(2.0, 1.0, 0.0));
+    }
+}
@@ -12,7 +12,7 @@ macro_rules! impl_index {
     ($ty:ident) => {
         impl Index for $ty {
             fn from_u64(i: u64) -> Result<$ty, Error> {
-                if  i < $ty::max_value() as u64 {
+                if i <= $ty::MAX as u64 {
                     Ok(i as _)
                 } else {
                     let bits_available = std::mem::size_of::<$ty>() as u8 * 8;
@@ -28,3 +28,16 @@ impl_index!(u32);
 impl_index!(u16);
 impl_index!(u8);
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts_the_largest_value() {
+        assert_eq!(u8::from_u64(255).unwrap(), 255);
+        assert!(u8::from_u64(256).is_err());
+        assert_eq!(u16::from_u64(65535).unwrap(), 65535);
+        assert_eq!(u64::from_u64(u64::MAX).unwrap(), u64::MAX);
+    }
+}
@@ -0,0 +1,64 @@
+//! Conversions for `cgmath`.
+
+use {Vector, Color, Matrix4, Quaternion};
+use cgmath::{Vector3, Point3, Matrix4 as CgmathMatrix4, Quaternion as CgmathQuaternion};
+
+impl From<Vector3<f32>> for Vector {
+    fn from(v: Vector3<f32>) -> Vector { Vector(v.x, v.y, v.z) }
+}
+
+impl From<Vector> for Vector3<f32> {
+    fn from(v: Vector) -> Self { Vector3::new(v.0, v.1, v.2) }
+}
+
+impl From<Point3<f32>> for Vector {
+    fn from(p: Point3<f32>) -> Vector { Vector(p.x, p.y, p.z) }
+}
+
+impl From<Vector> for Point3<f32> {
+    fn from(v: Vector) -> Self { Point3::new(v.0, v.1, v.2) }
+}
+
+impl From<Vector3<f32>> for Color {
+    fn from(v: Vector3<f32>) -> Color { Color(v.x, v.y, v.z) }
+}
+
+impl From<Color> for Vector3<f32> {
+    fn from(c: Color) -> Self { Vector3::new(c.0, c.1, c.2) }
+}
+
+impl From<CgmathMatrix4<f32>> for Matrix4 {
+    fn from(m: CgmathMatrix4<f32>) -> Matrix4 { Matrix4(m.into()) }
+}
+
+impl From<Matrix4> for CgmathMatrix4<f32> {
+    fn from(m: Matrix4) -> Self { m.0.into() }
+}
+
+impl From<CgmathQuaternion<f32>> for Quaternion {
+    fn from(q: CgmathQuaternion<f32>) -> Quaternion { Quaternion(q.v.x, q.v.y, q.v.z, q.s) }
+}
+
+impl From<Quaternion> for CgmathQuaternion<f32> {
+    fn from(q: Quaternion) -> Self { CgmathQuaternion::new(q.3, q.0, q.1, q.2) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::{Rad, Rotation3, Transform};
+
+    #[test]
+    fn agrees_with_cgmath() {
+        let rotation = Quaternion::from_axis_angle(Vector(0.0, 0.0, 1.0), 0.5);
+        let expected = CgmathQuaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), Rad(0.5));
+        assert!((CgmathQuaternion::from(rotation) - expected).s.abs() < 1e-6);
+
+        let matrix = Matrix4::from_translation(Vector(1.0, 2.0, 3.0)) * Matrix4::from_rotation(rotation);
+        let expected = CgmathMatrix4::from_translation(Vector3::new(1.0, 2.0, 3.0)) * CgmathMatrix4::from(expected);
+        let p = Point3::new(4.0, 5.0, 6.0);
+        let transformed: Vector = expected.transform_point(p).into();
+        assert!((transformed - matrix.transform_point(p.into())).length() < 1e-5);
+        assert_eq!(Matrix4::from(CgmathMatrix4::from(matrix)), matrix);
+    }
+}
@@ -0,0 +1,62 @@
+//! Conversions for `glam`.
+
+use {Vector, Color, Matrix4, Quaternion};
+use glam::{Vec3, Vec3A, Mat4, Quat};
+
+impl From<Vec3> for Vector {
+    fn from(v: Vec3) -> Vector { Vector(v.x, v.y, v.z) }
+}
+
+impl From<Vector> for Vec3 {
+    fn from(v: Vector) -> Self { Vec3::new(v.0, v.1, v.2) }
+}
+
+impl From<Vec3A> for Vector {
+    fn from(v: Vec3A) -> Vector { Vector(v.x, v.y, v.z) }
+}
+
+impl From<Vector> for Vec3A {
+    fn from(v: Vector) -> Self { Vec3A::new(v.0, v.1, v.2) }
+}
+
+impl From<Vec3> for Color {
+    fn from(v: Vec3) -> Color { Color(v.x, v.y, v.z) }
+}
+
+impl From<Color> for Vec3 {
+    fn from(c: Color) -> Self { Vec3::new(c.0, c.1, c.2) }
+}
+
+impl From<Mat4> for Matrix4 {
+    fn from(m: Mat4) -> Matrix4 { Matrix4(m.to_cols_array_2d()) }
+}
+
+impl From<Matrix4> for Mat4 {
+    fn from(m: Matrix4) -> Self { Mat4::from_cols_array_2d(&m.0) }
+}
+
+impl From<Quat> for Quaternion {
+    fn from(q: Quat) -> Quaternion { Quaternion(q.x, q.y, q.z, q.w) }
+}
+
+impl From<Quaternion> for Quat {
+    fn from(q: Quaternion) -> Self { Quat::from_xyzw(q.0, q.1, q.2, q.3) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn agrees_with_glam() {
+        let rotation = Quaternion::from_axis_angle(Vector(0.0, 1.0, 0.0), 0.5);
+        let matrix = Matrix4::from_translation_rotation_scale(Vector(1.0, 2.0, 3.0), rotation, Vector(1.0, 2.0, 1.0));
+        let expected = Mat4::from_scale_rotation_translation(Vec3::new(1.0, 2.0, 1.0), rotation.into(), Vec3::new(1.0, 2.0, 3.0));
+        assert!(Mat4::from(matrix).abs_diff_eq(expected, 1e-6));
+
+        let p = Vector(4.0, 5.0, 6.0);
+        let transformed: Vector = expected.transform_point3(p.into()).into();
+        assert!((transformed - matrix.transform_point(p)).length() < 1e-5);
+        assert_eq!(Matrix4::from(Mat4::from(matrix)), matrix);
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions for `mint`.
+
+use {Vertex, Vector, Color, Matrix4, Quaternion, TransformVertex};
+use mint::{Vector3, Point3, ColumnMatrix4, Quaternion as MintQuaternion};
+
+impl From<Vector3<f32>> for Vector {
+    fn from(v: Vector3<f32>) -> Vector { Vector(v.x, v.y, v.z) }
+}
+
+impl From<Vector> for Vector3<f32> {
+    fn from(v: Vector) -> Self { Vector3 { x: v.0, y: v.1, z: v.2 } }
+}
+
+impl From<Point3<f32>> for Vector {
+    fn from(p: Point3<f32>) -> Vector { Vector(p.x, p.y, p.z) }
+}
+
+impl From<Vector> for Point3<f32> {
+    fn from(v: Vector) -> Self { Point3 { x: v.0, y: v.1, z: v.2 } }
+}
+
+impl From<Vector3<f32>> for Color {
+    fn from(v: Vector3<f32>) -> Color { Color(v.x, v.y, v.z) }
+}
+
+impl From<Color> for Vector3<f32> {
+    fn from(c: Color) -> Self { Vector3 { x: c.0, y: c.1, z: c.2 } }
+}
+
+impl From<ColumnMatrix4<f32>> for Matrix4 {
+    fn from(m: ColumnMatrix4<f32>) -> Matrix4 { Matrix4(m.into()) }
+}
+
+impl From<Matrix4> for ColumnMatrix4<f32> {
+    fn from(m: Matrix4) -> Self { m.0.into() }
+}
+
+impl From<MintQuaternion<f32>> for Quaternion {
+    fn from(q: MintQuaternion<f32>) -> Quaternion { Quaternion(q.v.x, q.v.y, q.v.z, q.s) }
+}
+
+impl From<Quaternion> for MintQuaternion<f32> {
+    fn from(q: Quaternion) -> Self { MintQuaternion { v: Vector3 { x: q.0, y: q.1, z: q.2 }, s: q.3 } }
+}
+
+impl Vertex for Vector3<f32> {
+    fn position(&self) -> Vector { (*self).into() }
+}
+
+impl Vertex for Point3<f32> {
+    fn position(&self) -> Vector { (*self).into() }
+}
+
+impl TransformVertex for Vector3<f32> {
+    fn transform(&self, matrix: &Matrix4) -> Self { matrix.transform_point((*self).into()).into() }
+}
+
+impl TransformVertex for Point3<f32> {
+    fn transform(&self, matrix: &Matrix4) -> Self { matrix.transform_point((*self).into()).into() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use build;
+
+    #[test]
+    fn round_trips() {
+        let matrix = Matrix4::from_translation(Vector(1.0, 2.0, 3.0));
+        assert_eq!(Matrix4::from(ColumnMatrix4::from(matrix)), matrix);
+        assert_eq!(ColumnMatrix4::from(matrix).w, [1.0, 2.0, 3.0, 1.0].into());
+
+        let rotation = Quaternion(0.1, 0.2, 0.3, 0.9);
+        assert_eq!(MintQuaternion::from(rotation).s, 0.9);
+        assert_eq!(Quaternion::from(MintQuaternion::from(rotation)), rotation);
+    }
+
+    #[test]
+    fn vectors_are_vertices() {
+        let cube = build::unit_cube::<Vector3<f32>, u16>();
+        assert_eq!(cube.vertices[6].position(), Vector(-1.0, 1.0, 1.0));
+    }
+}
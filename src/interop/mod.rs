@@ -0,0 +1,18 @@
+//! Conversions to and from the types of other math libraries.
+//!
+//! Each library is enabled by the cargo feature of the same name. The
+//! vector types of `mint` and `nalgebra` can also be used as vertices
+//! directly; those of `glam` and `cgmath` cannot, as they do not
+//! implement `PartialOrd`.
+
+#[cfg(feature = "mint")]
+mod mint;
+
+#[cfg(feature = "glam")]
+mod glam;
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+
+#[cfg(feature = "cgmath")]
+mod cgmath;
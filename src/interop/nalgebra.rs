@@ -0,0 +1,94 @@
+//! Conversions for `nalgebra`.
+
+use {Vertex, Vector, Color, Matrix4, Quaternion, TransformVertex};
+use nalgebra::{Vector3, Point3, Matrix4 as NalgebraMatrix4, Quaternion as NalgebraQuaternion, UnitQuaternion};
+
+impl From<Vector3<f32>> for Vector {
+    fn from(v: Vector3<f32>) -> Vector { Vector(v.x, v.y, v.z) }
+}
+
+impl From<Vector> for Vector3<f32> {
+    fn from(v: Vector) -> Self { Vector3::new(v.0, v.1, v.2) }
+}
+
+impl From<Point3<f32>> for Vector {
+    fn from(p: Point3<f32>) -> Vector { Vector(p.x, p.y, p.z) }
+}
+
+impl From<Vector> for Point3<f32> {
+    fn from(v: Vector) -> Self { Point3::new(v.0, v.1, v.2) }
+}
+
+impl From<Vector3<f32>> for Color {
+    fn from(v: Vector3<f32>) -> Color { Color(v.x, v.y, v.z) }
+}
+
+impl From<Color> for Vector3<f32> {
+    fn from(c: Color) -> Self { Vector3::new(c.0, c.1, c.2) }
+}
+
+impl From<NalgebraMatrix4<f32>> for Matrix4 {
+    fn from(m: NalgebraMatrix4<f32>) -> Matrix4 { Matrix4(m.into()) }
+}
+
+impl From<Matrix4> for NalgebraMatrix4<f32> {
+    fn from(m: Matrix4) -> Self { m.0.into() }
+}
+
+impl From<NalgebraQuaternion<f32>> for Quaternion {
+    fn from(q: NalgebraQuaternion<f32>) -> Quaternion { Quaternion(q.i, q.j, q.k, q.w) }
+}
+
+impl From<Quaternion> for NalgebraQuaternion<f32> {
+    fn from(q: Quaternion) -> Self { NalgebraQuaternion::new(q.3, q.0, q.1, q.2) }
+}
+
+impl From<UnitQuaternion<f32>> for Quaternion {
+    fn from(q: UnitQuaternion<f32>) -> Quaternion { q.into_inner().into() }
+}
+
+/// Normalizes the quaternion, as `UnitQuaternion` requires.
+impl From<Quaternion> for UnitQuaternion<f32> {
+    fn from(q: Quaternion) -> Self { UnitQuaternion::from_quaternion(q.into()) }
+}
+
+impl Vertex for Vector3<f32> {
+    fn position(&self) -> Vector { (*self).into() }
+}
+
+impl Vertex for Point3<f32> {
+    fn position(&self) -> Vector { (*self).into() }
+}
+
+impl TransformVertex for Vector3<f32> {
+    fn transform(&self, matrix: &Matrix4) -> Self { matrix.transform_point((*self).into()).into() }
+}
+
+impl TransformVertex for Point3<f32> {
+    fn transform(&self, matrix: &Matrix4) -> Self { matrix.transform_point((*self).into()).into() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use build;
+
+    #[test]
+    fn agrees_with_nalgebra() {
+        let rotation = Quaternion::from_axis_angle(Vector(1.0, 0.0, 0.0), 0.5);
+        let matrix = Matrix4::from_translation(Vector(1.0, 2.0, 3.0)) * Matrix4::from_rotation(rotation);
+        let expected = NalgebraMatrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0))
+            * UnitQuaternion::from(rotation).to_homogeneous();
+        assert!((NalgebraMatrix4::from(matrix) - expected).abs().max() < 1e-6);
+
+        let p = Point3::new(4.0, 5.0, 6.0);
+        let transformed: Vector = expected.transform_point(&p).into();
+        assert!((transformed - matrix.transform_point(p.into())).length() < 1e-5);
+    }
+
+    #[test]
+    fn points_are_vertices() {
+        let cube = build::unit_cube::<Point3<f32>, u16>();
+        assert_eq!(cube.vertices[6].position(), Vector(-1.0, 1.0, 1.0));
+    }
+}
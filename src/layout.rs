@@ -0,0 +1,390 @@
+//! Describing and packing vertex buffers for upload to a GPU.
+//!
+//! A `VertexLayout` lists the attributes of a vertex, how each is encoded and
+//! where it sits in the vertex. `interleave` packs a mesh into a single
+//! buffer following the layout, and `deinterleave` packs it into one
+//! buffer per attribute instead.
+//!
+//! ```
+//! use mash::{build, Vector, TriangularMesh};
+//! use mash::layout::{self, VertexLayout, Semantic, ComponentType};
+//!
+//! let cube: TriangularMesh<mash::load::Vertex, u16> = build::cube_with_normals(1.0);
+//! let layout = VertexLayout::new()
+//!     .attribute(Semantic::Position, ComponentType::Float32, 3)
+//!     .attribute(Semantic::Normal, ComponentType::Snorm8, 4);
+//!
+//! assert_eq!(layout.stride, 16);
+//! assert_eq!(layout::interleave(&cube, &layout).unwrap().len(), 24 * 16);
+//! assert_eq!(layout::index_buffer(&cube).len(), 36 * 2);
+//! ```
+
+use {TriangularMesh, Vertex, Vector, Index, Error, ErrorKind};
+
+use std::mem;
+
+/// What a vertex attribute holds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Semantic {
+    Position,
+    Normal,
+    TexCoords,
+    /// The color, with an alpha of one.
+    Color,
+    /// The tangent, with the bitangent sign in the fourth component.
+    Tangent,
+}
+
+/// How each component of an attribute is stored.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentType {
+    /// A 32-bit float.
+    Float32,
+    /// A 16-bit IEEE 754 half-precision float.
+    Float16,
+    /// An unsigned byte that maps `0..255` to `0.0..1.0`.
+    Unorm8,
+    /// A signed byte that maps `-127..127` to `-1.0..1.0`.
+    Snorm8,
+    /// An unsigned short that maps `0..65535` to `0.0..1.0`.
+    Unorm16,
+    /// A signed short that maps `-32767..32767` to `-1.0..1.0`.
+    Snorm16,
+}
+
+/// One attribute of a vertex layout.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Attribute {
+    pub semantic: Semantic,
+    pub component_type: ComponentType,
+    /// The number of components, from one to four.
+    pub components: usize,
+    /// The offset of the attribute from the start of the vertex, in bytes.
+    pub offset: usize,
+}
+
+/// The attributes of a vertex and the distance between vertices.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    pub attributes: Vec<Attribute>,
+    /// The size of a vertex, including any padding, in bytes.
+    pub stride: usize,
+}
+
+/// A vertex type with a natural layout, such as its own fields.
+///
+/// `#[derive(Vertex)]` implements this with an attribute for every marked
+/// field, in order, stored as 32-bit floats.
+pub trait HasLayout : Vertex {
+    /// Gets the layout of the vertex type.
+    fn layout() -> VertexLayout;
+}
+
+impl ComponentType {
+    /// Gets the size of one component, in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            ComponentType::Float32 => 4,
+            ComponentType::Float16 | ComponentType::Unorm16 | ComponentType::Snorm16 => 2,
+            ComponentType::Unorm8 | ComponentType::Snorm8 => 1,
+        }
+    }
+
+    fn encode(self, value: f32, bytes: &mut Vec<u8>) {
+        match self {
+            ComponentType::Float32 => bytes.extend_from_slice(&value.to_le_bytes()),
+            ComponentType::Float16 => bytes.extend_from_slice(&half(value).to_le_bytes()),
+            ComponentType::Unorm8 => bytes.push((value.clamp(0.0, 1.0) * 255.0).round() as u8),
+            ComponentType::Snorm8 => bytes.push((value.clamp(-1.0, 1.0) * 127.0).round() as i8 as u8),
+            ComponentType::Unorm16 => bytes.extend_from_slice(&((value.clamp(0.0, 1.0) * 65535.0).round() as u16).to_le_bytes()),
+            ComponentType::Snorm16 => bytes.extend_from_slice(&((value.clamp(-1.0, 1.0) * 32767.0).round() as i16).to_le_bytes()),
+        }
+    }
+}
+
+impl Attribute {
+    /// Gets the size of the attribute, in bytes.
+    pub fn size(&self) -> usize {
+        self.component_type.size() * self.components
+    }
+
+    /// Appends the encoded attribute of a vertex to a buffer.
+    fn encode<V: Vertex>(&self, vertex: &V, bytes: &mut Vec<u8>) {
+        let values = attribute_values(self.semantic, vertex);
+        for i in 0..self.components {
+            // Missing components are zero, except the fourth, which is one as
+            // for the `w` of a point or the alpha of a color.
+            let value = values.get(i).cloned().unwrap_or(if i == 3 { 1.0 } else { 0.0 });
+            self.component_type.encode(value, bytes);
+        }
+    }
+}
+
+impl VertexLayout {
+    /// Creates a layout without any attributes.
+    pub fn new() -> Self {
+        VertexLayout::default()
+    }
+
+    /// Gets the layout of a vertex type.
+    pub fn of<V: HasLayout>() -> Self {
+        V::layout()
+    }
+
+    /// Appends an attribute directly after the existing ones, growing the stride to fit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `components` is not between one and four.
+    pub fn attribute(mut self, semantic: Semantic, component_type: ComponentType, components: usize) -> Self {
+        assert!((1..=4).contains(&components), "attributes have between one and four components");
+
+        let offset = self.attributes.iter().map(|a| a.offset + a.size()).max().unwrap_or(0);
+        let attribute = Attribute { semantic, component_type, components, offset };
+        self.stride = self.stride.max(offset + attribute.size());
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Pads the stride up to a multiple of `alignment` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` is zero.
+    pub fn aligned(mut self, alignment: usize) -> Self {
+        assert!(alignment > 0, "the alignment must be at least one byte");
+        self.stride = self.stride.div_ceil(alignment) * alignment;
+        self
+    }
+
+    /// Checks that every attribute has between one and four components and
+    /// fits within the stride.
+    ///
+    /// Layouts built with `attribute` are always valid, but the fields can
+    /// also be set directly.
+    pub fn validate(&self) -> Result<(), Error> {
+        for attribute in self.attributes.iter() {
+            if !(1..=4).contains(&attribute.components) {
+                return Err(ErrorKind::InvalidLayout(format!("{:?} attribute has {} components", attribute.semantic, attribute.components)).into());
+            }
+            if attribute.offset.checked_add(attribute.size()).is_none_or(|end| end > self.stride) {
+                return Err(ErrorKind::InvalidLayout(format!("{:?} attribute at offset {} does not fit in the stride of {}",
+                                                            attribute.semantic, attribute.offset, self.stride)).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the attribute with a semantic, if the layout has one.
+    pub fn get(&self, semantic: Semantic) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.semantic == semantic)
+    }
+}
+
+/// Packs the vertices of a mesh into a single buffer, following a layout.
+///
+/// Bytes that no attribute covers are zero. Fails with
+/// `ErrorKind::InvalidLayout` if the layout does not pass `validate`.
+pub fn interleave<V: Vertex, I: Index>(mesh: &TriangularMesh<V, I>, layout: &VertexLayout) -> Result<Vec<u8>, Error> {
+    layout.validate()?;
+
+    let mut bytes = vec![0; mesh.vertices.len() * layout.stride];
+    let mut attribute_bytes = Vec::new();
+
+    for (vertex, slot) in mesh.vertices.iter().zip(bytes.chunks_mut(layout.stride.max(1))) {
+        for attribute in layout.attributes.iter() {
+            attribute_bytes.clear();
+            attribute.encode(vertex, &mut attribute_bytes);
+            slot[attribute.offset..attribute.offset + attribute_bytes.len()].copy_from_slice(&attribute_bytes);
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Packs the vertices of a mesh into one tightly packed buffer per
+/// attribute, in the order of the layout's attributes.
+///
+/// Offsets and the stride of the layout are ignored.
+pub fn deinterleave<V: Vertex, I: Index>(mesh: &TriangularMesh<V, I>, layout: &VertexLayout) -> Vec<Vec<u8>> {
+    layout.attributes.iter().map(|attribute| {
+        let mut bytes = Vec::with_capacity(mesh.vertices.len() * attribute.size());
+        for vertex in mesh.vertices.iter() {
+            attribute.encode(vertex, &mut bytes);
+        }
+        bytes
+    }).collect()
+}
+
+/// Packs the indices of a mesh into a buffer, using the size of the index type.
+pub fn index_buffer<V: Vertex, I: Index>(mesh: &TriangularMesh<V, I>) -> Vec<u8> {
+    let size = mem::size_of::<I>();
+    let mut bytes = Vec::with_capacity(mesh.indices.len() * size);
+    for &index in mesh.indices.iter() {
+        let index: u64 = index.into();
+        bytes.extend_from_slice(&index.to_le_bytes()[..size]);
+    }
+    bytes
+}
+
+impl HasLayout for Vector {
+    fn layout() -> VertexLayout {
+        VertexLayout::new().attribute(Semantic::Position, ComponentType::Float32, 3)
+    }
+}
+
+fn attribute_values<V: Vertex>(semantic: Semantic, vertex: &V) -> Vec<f32> {
+    match semantic {
+        Semantic::Position => {
+            let p = vertex.position();
+            vec![p.0, p.1, p.2]
+        },
+        Semantic::Normal => vertex.normal().map(|n| vec![n.0, n.1, n.2]).unwrap_or_default(),
+        Semantic::TexCoords => vertex.texture_coords().map(|t| t.to_vec()).unwrap_or_default(),
+        Semantic::Color => vertex.color().map(|c| vec![c.0, c.1, c.2]).unwrap_or_default(),
+        Semantic::Tangent => vertex.tangent().map(|t| t.to_vec()).unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]),
+    }
+}
+
+/// Converts a float to the bits of the nearest half-precision float.
+fn half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity stays infinite, and NaN stays NaN.
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Rounds away the low `shift` bits, to the nearest even on ties.
+    let round = |mantissa: u32, shift: u32| {
+        let (kept, rest, halfway) = (mantissa >> shift, mantissa & ((1 << shift) - 1), 1 << (shift - 1));
+        if rest > halfway || (rest == halfway && kept & 1 == 1) { kept + 1 } else { kept }
+    };
+
+    if exponent <= 0 {
+        // Too small for a normal half, so store it as a subnormal.
+        if exponent < -10 {
+            return sign;
+        }
+        return sign | round(mantissa | 0x80_0000, (14 - exponent) as u32) as u16;
+    }
+
+    // A rounding carry out of the mantissa correctly bumps the exponent.
+    sign | (((exponent as u32) << 10) + round(mantissa, 13)) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Color, HasColor, HasNormal, HasTexCoords};
+    use load;
+
+    fn triangle() -> TriangularMesh<load::Vertex, u16> {
+        let vertex = |x: f32| {
+            let mut vertex = load::Vertex::from(Vector(x, 1.0, -1.0));
+            vertex.set_normal(Vector(0.0, -1.0, 0.0));
+            vertex.set_texture_coords([x, 0.5]);
+            vertex.set_color(Color(1.0, 0.5, 0.0));
+            vertex
+        };
+        TriangularMesh { vertices: vec![vertex(0.0), vertex(1.0), vertex(2.0)], indices: vec![0, 1, 2] }
+    }
+
+    #[test]
+    fn packs_attributes_tightly() {
+        let layout = VertexLayout::new()
+            .attribute(Semantic::Position, ComponentType::Float32, 3)
+            .attribute(Semantic::Normal, ComponentType::Snorm8, 4)
+            .attribute(Semantic::TexCoords, ComponentType::Unorm16, 2)
+            .attribute(Semantic::Color, ComponentType::Unorm8, 4);
+
+        assert_eq!(layout.attributes.iter().map(|a| a.offset).collect::<Vec<_>>(), vec![0, 12, 16, 20]);
+        assert_eq!(layout.stride, 24);
+        assert_eq!(layout.clone().aligned(16).stride, 32);
+        assert_eq!(layout.get(Semantic::Normal).unwrap().size(), 4);
+        assert_eq!(layout.get(Semantic::Tangent), None);
+
+        let bytes = interleave(&triangle(), &layout).unwrap();
+        assert_eq!(bytes.len(), 3 * 24);
+        assert_eq!(&bytes[24..48], &[
+            0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x80, 0xbf,
+            0x00, 0x81, 0x00, 0x7f,
+            0xff, 0xff, 0x00, 0x80,
+            0xff, 0x80, 0x00, 0xff,
+        ][..]);
+    }
+
+    #[test]
+    fn deinterleaves_one_buffer_per_attribute() {
+        let layout = VertexLayout::new()
+            .attribute(Semantic::TexCoords, ComponentType::Float16, 2)
+            .attribute(Semantic::Tangent, ComponentType::Snorm16, 4);
+
+        let buffers = deinterleave(&triangle(), &layout);
+        assert_eq!(buffers.len(), 2);
+        assert_eq!(buffers[0], vec![0x00, 0x00, 0x00, 0x38, 0x00, 0x3c, 0x00, 0x38, 0x00, 0x40, 0x00, 0x38]);
+        assert_eq!(&buffers[1][..8], &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x7f][..]);
+        assert_eq!(buffers[1].len(), 3 * 8);
+    }
+
+    #[test]
+    fn respects_custom_offsets() {
+        let layout = VertexLayout {
+            attributes: vec![Attribute { semantic: Semantic::Position, component_type: ComponentType::Float32, components: 1, offset: 4 }],
+            stride: 12,
+        };
+
+        let bytes = interleave(&triangle(), &layout).unwrap();
+        assert_eq!(&bytes[12..24], &[0, 0, 0, 0, 0x00, 0x00, 0x80, 0x3f, 0, 0, 0, 0][..]);
+    }
+
+    #[test]
+    fn rejects_attributes_past_the_stride() {
+        let mut layout = VertexLayout::new().attribute(Semantic::Position, ComponentType::Float32, 3);
+        layout.attributes[0].offset = 4;
+
+        match interleave(&triangle(), &layout) {
+            Err(Error(ErrorKind::InvalidLayout(..), _)) => (),
+            result => panic!("expected an invalid layout but got {:?}", result),
+        }
+
+        layout.stride = 16;
+        layout.attributes[0].components = 5;
+        assert!(layout.validate().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one byte")]
+    fn rejects_zero_alignment() {
+        VertexLayout::new().attribute(Semantic::Position, ComponentType::Float32, 3).aligned(0);
+    }
+
+    #[test]
+    fn packs_indices() {
+        assert_eq!(index_buffer(&triangle()), vec![0, 0, 1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn converts_to_half_floats() {
+        assert_eq!(half(1.0), 0x3c00);
+        assert_eq!(half(-2.0), 0xc000);
+        assert_eq!(half(0.333_251_95), 0x3555);
+        assert_eq!(half(65504.0), 0x7bff);
+        assert_eq!(half(1.0e6), 0x7c00);
+        assert_eq!(half(1.0e-7), 0x0002);
+        assert_eq!(half(1.0e-9), 0x0000);
+        assert_eq!(half(-0.0), 0x8000);
+        assert!(half(f32::NAN) & 0x7fff > 0x7c00);
+        // Halfway between 1.0 and the next half rounds to even.
+        assert_eq!(half(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(half(1.0 + 3.0 / 2048.0), 0x3c02);
+    }
+}
@@ -29,6 +29,9 @@
 //! let model: Model<Vertex, Index> = Model::new(load::wavefront::from_path("res/cube.obj").unwrap()).unwrap();
 //! ```
 //!
+//! With the `derive` feature, `#[derive(mash::Vertex)]` writes these impls
+//! from `#[mash(position)]`, `#[mash(normal)]` and similar field attributes.
+//!
 //! # Preprocessing meshes
 //!
 //! ```
@@ -48,18 +51,50 @@
 
 #[macro_use] extern crate error_chain;
 
-#[cfg(feature = "wavefront")]
-extern crate tobj;
+#[cfg(feature = "gltf")]
+extern crate gltf;
+#[cfg(feature = "gltf")]
+extern crate base64;
+
+#[cfg(feature = "derive")]
+extern crate mash_derive;
+
+#[cfg(feature = "mint")]
+extern crate mint;
+#[cfg(feature = "glam")]
+extern crate glam;
+#[cfg(feature = "nalgebra")]
+extern crate nalgebra;
+#[cfg(feature = "cgmath")]
+extern crate cgmath;
 
 pub use self::errors::{Error, ErrorKind, ResultExt, Result};
-pub use self::geometry::{Vertex, Vector, Triangle, Color};
+pub use self::geometry::{Vertex, HasNormal, HasTexCoords, HasColor, HasTangent};
+pub use self::geometry::{Vector, Triangle, Color, Bounds, Matrix4, Quaternion};
 pub use self::index::Index;
-pub use self::model::{Model, TriangularMesh, BuildModel};
+pub use self::model::{Model, TriangularMesh, MeshBuilder, Submesh, BuildModel};
+pub use self::material::{Material, AlphaMode, Texture, ImageSource};
+pub use self::scene::{Scene, BuildScene, TransformVertex};
+pub use self::layout::{VertexLayout, HasLayout};
+
+#[cfg(feature = "derive")]
+pub use mash_derive::Vertex;
 
 pub mod errors;
 pub mod geometry;
 pub mod index;
 pub mod model;
+pub mod material;
+pub mod scene;
+pub mod layout;
+pub mod process;
+pub mod optimize;
 pub mod load;
+pub mod save;
 pub mod build;
 
+mod interop;
+
+#[cfg(feature = "derive")]
+mod derive;
+
@@ -0,0 +1,725 @@
+//! Loader for the glTF 2.0 file format.
+//!
+//! Both the JSON (`.gltf`) and binary (`.glb`) containers are supported.
+//! Buffers may be embedded in the binary chunk, stored in base64 `data:`
+//! URIs, or stored in external files next to the document.
+
+use {Model, TriangularMesh, BuildModel, Scene, BuildScene, Vector, Matrix4, Index, Error, ErrorKind};
+use scene;
+use gltf;
+use gltf::mesh::Mode;
+use base64;
+use base64::Engine;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// A glTF document along with all of its buffer data.
+pub struct Gltf {
+    document: gltf::Document,
+    buffers: Vec<Vec<u8>>,
+    /// The directory of the document, if it was loaded from disk.
+    directory: Option<PathBuf>,
+}
+
+/// A vertex.
+pub struct Vertex {
+    pub position: Vector,
+    pub normal: Option<Vector>,
+    /// The tangent, with the bitangent sign stored in `w`.
+    pub tangent: Option<[f32; 4]>,
+    /// The `TEXCOORD_n` attributes, indexed by `n`.
+    pub texture_coords: Vec<[f32; 2]>,
+    /// The `COLOR_n` attributes as RGBA, indexed by `n`.
+    pub colors: Vec<[f32; 4]>,
+}
+
+/// A vertex attribute present on a primitive.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Attribute {
+    /// `POSITION`
+    Position,
+    /// `NORMAL`
+    Normal,
+    /// `TANGENT`
+    Tangent,
+    /// `TEXCOORD_n`
+    TexCoords(u32),
+    /// `COLOR_n`
+    Color(u32),
+    /// `JOINTS_n`
+    Joints(u32),
+    /// `WEIGHTS_n`
+    Weights(u32),
+}
+
+/// A mesh, made up of one or more primitives.
+pub struct Mesh<'a> {
+    gltf: &'a Gltf,
+    mesh: gltf::Mesh<'a>,
+}
+
+/// A single draw call's worth of geometry within a mesh.
+pub struct Primitive<'a> {
+    gltf: &'a Gltf,
+    primitive: gltf::Primitive<'a>,
+}
+
+/// A node in the scene hierarchy.
+pub struct Node<'a> {
+    gltf: &'a Gltf,
+    node: gltf::Node<'a>,
+}
+
+/// Creates an accessor reader for a `Primitive`, backed by the loaded buffers.
+macro_rules! reader {
+    ($primitive:expr) => {
+        {
+            let buffers = &$primitive.gltf.buffers;
+            $primitive.primitive.reader(move |buffer| buffers.get(buffer.index()).map(Vec::as_slice))
+        }
+    }
+}
+
+/// Loads a glTF `.gltf` or `.glb` file from disk.
+///
+/// External buffers are loaded relative to the directory of the file.
+pub fn from_path<S>(path: S) -> Result<Gltf, Error>
+    where S: AsRef<Path> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or(Path::new("")).to_owned();
+
+    let mut file = File::open(path)?;
+    let mut gltf = from_memory(&mut file, |uri| File::open(directory.join(uri)))?;
+    gltf.directory = Some(directory);
+    Ok(gltf)
+}
+
+/// Loads a glTF `.gltf` or `.glb` file from memory.
+///
+/// You must provide a closure that maps each external buffer URI
+/// to its corresponding byte stream.
+pub fn from_memory<R, B>(reader: &mut R,
+                         buffer_loader: impl Fn(&Path) -> io::Result<B>)
+    -> Result<Gltf, Error>
+    where R: Read, B: Read {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(&bytes)?;
+
+    let buffers: Result<Vec<_>, Error> = document.buffers().map(|buffer| {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => {
+                blob.take().ok_or_else(|| invalid("buffer refers to a missing binary chunk".to_owned()))?
+            },
+            gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                let (_, encoded) = uri.split_once(";base64,")
+                    .ok_or_else(|| invalid("only base64 data URIs are supported".to_owned()))?;
+                base64::engine::general_purpose::STANDARD.decode(encoded)
+                    .map_err(|e| invalid(format!("invalid base64 in data URI: {}", e)))?
+            },
+            gltf::buffer::Source::Uri(uri) => {
+                let mut data = Vec::new();
+                buffer_loader(Path::new(uri))?.read_to_end(&mut data)?;
+                data
+            },
+        };
+
+        if data.len() < buffer.length() {
+            return Err(invalid(format!("buffer {} is {} bytes but should be {}",
+                                       buffer.index(), data.len(), buffer.length())));
+        }
+
+        Ok(data)
+    }).collect();
+
+    Ok(Gltf { document, buffers: buffers?, directory: None })
+}
+
+impl Gltf {
+    /// All of the meshes in the document.
+    pub fn meshes(&self) -> impl Iterator<Item=Mesh<'_>> {
+        self.document.meshes().map(move |mesh| Mesh { gltf: self, mesh })
+    }
+
+    /// All of the nodes in the document, in declaration order.
+    pub fn nodes(&self) -> impl Iterator<Item=Node<'_>> {
+        self.document.nodes().map(move |node| Node { gltf: self, node })
+    }
+
+    /// The top-level nodes of the default scene.
+    ///
+    /// If the document does not specify a default scene, the first scene is
+    /// used. If there are no scenes at all, every node without a parent is
+    /// treated as a root.
+    pub fn root_nodes(&self) -> Vec<Node<'_>> {
+        match self.document.default_scene().or_else(|| self.document.scenes().next()) {
+            Some(scene) => scene.nodes().map(|node| Node { gltf: self, node }).collect(),
+            None => {
+                let mut is_child = vec![false; self.document.nodes().len()];
+                for child in self.document.nodes().flat_map(|n| n.children()) {
+                    is_child[child.index()] = true;
+                }
+
+                self.nodes().filter(|node| !is_child[node.index()]).collect()
+            },
+        }
+    }
+}
+
+impl<'a> Mesh<'a> {
+    /// Gets the index of the mesh within the document.
+    pub fn index(&self) -> usize { self.mesh.index() }
+
+    /// Gets the name of the mesh.
+    pub fn name(&self) -> Option<&'a str> { self.mesh.name() }
+
+    /// Gets all of the primitives that make up the mesh.
+    pub fn primitives(&self) -> impl Iterator<Item=Primitive<'a>> + 'a {
+        let gltf = self.gltf;
+        self.mesh.primitives().map(move |primitive| Primitive { gltf, primitive })
+    }
+}
+
+impl<'a> Primitive<'a> {
+    /// Gets the index of the primitive within its mesh.
+    pub fn index(&self) -> usize { self.primitive.index() }
+
+    /// Gets the index of the material used by the primitive.
+    pub fn material_index(&self) -> Option<usize> { self.primitive.material().index() }
+
+    /// Checks whether the primitive is made up of triangles.
+    ///
+    /// Only triangle primitives can be built into a model.
+    pub fn is_triangles(&self) -> bool {
+        matches!(self.primitive.mode(), Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan)
+    }
+
+    /// Gets all of the vertex attributes present on the primitive.
+    pub fn attributes(&self) -> Vec<Attribute> {
+        let mut attributes: Vec<_> = self.primitive.attributes().map(|(semantic, _)| {
+            match semantic {
+                gltf::Semantic::Positions => Attribute::Position,
+                gltf::Semantic::Normals => Attribute::Normal,
+                gltf::Semantic::Tangents => Attribute::Tangent,
+                gltf::Semantic::TexCoords(n) => Attribute::TexCoords(n),
+                gltf::Semantic::Colors(n) => Attribute::Color(n),
+                gltf::Semantic::Joints(n) => Attribute::Joints(n),
+                gltf::Semantic::Weights(n) => Attribute::Weights(n),
+            }
+        }).collect();
+
+        attributes.sort();
+        attributes
+    }
+
+    /// Reads the `POSITION` attribute.
+    pub fn positions(&self) -> Option<Vec<Vector>> {
+        reader!(self).read_positions().map(|iter| iter.map(|p| Vector(p[0], p[1], p[2])).collect())
+    }
+
+    /// Reads the `NORMAL` attribute.
+    pub fn normals(&self) -> Option<Vec<Vector>> {
+        reader!(self).read_normals().map(|iter| iter.map(|n| Vector(n[0], n[1], n[2])).collect())
+    }
+
+    /// Reads the `TANGENT` attribute.
+    pub fn tangents(&self) -> Option<Vec<[f32; 4]>> {
+        reader!(self).read_tangents().map(Iterator::collect)
+    }
+
+    /// Reads the `TEXCOORD_n` attribute, converting normalized integers to floats.
+    pub fn texture_coords(&self, set: u32) -> Option<Vec<[f32; 2]>> {
+        reader!(self).read_tex_coords(set).map(|iter| iter.into_f32().collect())
+    }
+
+    /// Reads the `COLOR_n` attribute as RGBA.
+    pub fn colors(&self, set: u32) -> Option<Vec<[f32; 4]>> {
+        reader!(self).read_colors(set).map(|iter| iter.into_rgba_f32().collect())
+    }
+
+    /// Reads the raw index list, if the primitive is indexed.
+    pub fn indices(&self) -> Option<Vec<u32>> {
+        reader!(self).read_indices().map(|iter| iter.into_u32().collect())
+    }
+
+    /// Gets all of the vertices of the primitive.
+    pub fn vertices(&self) -> Result<Vec<Vertex>, Error> {
+        let positions = self.positions()
+            .ok_or_else(|| invalid("primitive has no POSITION attribute".to_owned()))?;
+        let normals = self.normals();
+        let tangents = self.tangents();
+        let texture_coords: Vec<_> = (0..).map(|set| self.texture_coords(set)).take_while(Option::is_some).flatten().collect();
+        let colors: Vec<_> = (0..).map(|set| self.colors(set)).take_while(Option::is_some).flatten().collect();
+
+        // Every attribute is indexed by vertex, so each one must have a value
+        // for every position.
+        let lengths = normals.iter().map(|n| ("NORMAL".to_owned(), n.len()))
+            .chain(tangents.iter().map(|t| ("TANGENT".to_owned(), t.len())))
+            .chain(texture_coords.iter().enumerate().map(|(i, set)| (format!("TEXCOORD_{}", i), set.len())))
+            .chain(colors.iter().enumerate().map(|(i, set)| (format!("COLOR_{}", i), set.len())));
+        for (attribute, length) in lengths {
+            if length != positions.len() {
+                return Err(invalid(format!("{} has {} values but there are {} positions", attribute, length, positions.len())));
+            }
+        }
+
+        Ok(positions.into_iter().enumerate().map(|(i, position)| {
+            Vertex {
+                position,
+                normal: normals.as_ref().map(|n| n[i]),
+                tangent: tangents.as_ref().map(|t| t[i]),
+                texture_coords: texture_coords.iter().map(|set| set[i]).collect(),
+                colors: colors.iter().map(|set| set[i]).collect(),
+            }
+        }).collect())
+    }
+
+    /// Gets the vertex indices of every triangle, unrolling strips and fans.
+    fn triangle_indices(&self, vertex_count: usize) -> Result<Vec<u32>, Error> {
+        let indices = self.indices().unwrap_or_else(|| (0..vertex_count as u32).collect());
+
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+            return Err(invalid(format!("index {} is out of range for {} vertices", index, vertex_count)));
+        }
+
+        Ok(match self.primitive.mode() {
+            Mode::Triangles => indices,
+            Mode::TriangleStrip => (0..indices.len().saturating_sub(2)).flat_map(|i| {
+                // Every second triangle in a strip has reversed winding.
+                if i % 2 == 0 {
+                    vec![indices[i], indices[i + 1], indices[i + 2]]
+                } else {
+                    vec![indices[i + 1], indices[i], indices[i + 2]]
+                }
+            }).collect(),
+            Mode::TriangleFan => (1..indices.len().saturating_sub(1)).flat_map(|i| {
+                vec![indices[0], indices[i], indices[i + 1]]
+            }).collect(),
+            mode => return Err(invalid(format!("cannot build a triangle mesh out of a {:?} primitive", mode))),
+        })
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Gets the index of the node within the document.
+    pub fn index(&self) -> usize { self.node.index() }
+
+    /// Gets the name of the node.
+    pub fn name(&self) -> Option<&'a str> { self.node.name() }
+
+    /// Gets the local transform as a column-major matrix.
+    pub fn matrix(&self) -> [[f32; 4]; 4] { self.node.transform().matrix() }
+
+    /// Gets the local transform as `(translation, rotation, scale)`.
+    ///
+    /// The rotation is a unit quaternion in `[x, y, z, w]` order.
+    pub fn decomposed(&self) -> ([f32; 3], [f32; 4], [f32; 3]) { self.node.transform().decomposed() }
+
+    /// Gets the mesh instantiated by the node.
+    pub fn mesh(&self) -> Option<Mesh<'a>> {
+        let gltf = self.gltf;
+        self.node.mesh().map(|mesh| Mesh { gltf, mesh })
+    }
+
+    /// Gets the direct children of the node.
+    pub fn children(&self) -> impl Iterator<Item=Node<'a>> + 'a {
+        let gltf = self.gltf;
+        self.node.children().map(move |node| Node { gltf, node })
+    }
+}
+
+impl<'a> BuildModel for Primitive<'a> {
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut model = self.gltf.empty_model()?;
+        self.append_to(&mut model, "", |vertex| vertex)?;
+        Ok(model)
+    }
+}
+
+/// Builds every triangle primitive of a mesh into a single model,
+/// with a submesh for each primitive.
+impl<'a> BuildModel for Mesh<'a> {
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut model = self.gltf.empty_model()?;
+
+        for primitive in self.primitives().filter(Primitive::is_triangles) {
+            primitive.append_to(&mut model, self.name().unwrap_or(""), |vertex| vertex)?;
+        }
+
+        Ok(model)
+    }
+}
+
+/// Builds every mesh instance in the default scene into a single model,
+/// with a submesh for each primitive of each instance.
+///
+/// Node transforms are applied, so the model is in world space.
+impl BuildModel for Gltf {
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut model = self.empty_model()?;
+
+        for root in self.root_nodes() {
+            build_node(&root, Matrix4::IDENTITY, &mut model, &mut Vec::new())?;
+        }
+
+        Ok(model)
+    }
+}
+
+/// Builds the node hierarchy of the document, with a model for each mesh.
+///
+/// Scene node and model indices are the same as the glTF node and mesh
+/// indices, and the roots are the nodes of the default scene.
+impl BuildScene for Gltf {
+    type Vertex = Vertex;
+
+    fn build_scene<V,I>(self) -> Result<Scene<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut scene = Scene::empty();
+        scene.materials = self.material_table()?;
+
+        for mesh in self.meshes() {
+            let mut model: Model<V,I> = mesh.build_model()?;
+            model.materials.clear();
+            scene.add_model(model);
+        }
+
+        scene.nodes = self.nodes().map(|node| scene::Node {
+            name: node.name().unwrap_or("").to_owned(),
+            transform: Matrix4(node.matrix()),
+            children: node.children().map(|child| child.index()).collect(),
+            model: node.mesh().map(|mesh| mesh.index()),
+        }).collect();
+        scene.roots = self.root_nodes().iter().map(Node::index).collect();
+
+        Ok(scene)
+    }
+}
+
+impl<'a> Primitive<'a> {
+    /// Appends the primitive to a model as a submesh, transforming each vertex.
+    fn append_to<V,I,F>(&self, model: &mut Model<V,I>, name: &str, transform: F) -> Result<(), Error>
+        where V: ::Vertex + From<Vertex>, I: Index, F: Fn(Vertex) -> Vertex {
+        let vertices = self.vertices()?;
+        let first_vertex = model.mesh.vertices.len() as u64;
+        let index_offset = model.mesh.indices.len();
+
+        for index in self.triangle_indices(vertices.len())? {
+            // Each primitive has indices relative to itself.
+            model.mesh.indices.push(I::from_u64(first_vertex + index as u64)?);
+        }
+        model.mesh.vertices.extend(vertices.into_iter().map(|vertex| V::from(transform(vertex))));
+
+        let index_count = model.mesh.indices.len() - index_offset;
+        model.submeshes.push(::Submesh {
+            name: name.to_owned(),
+            index_offset,
+            index_count,
+            material: self.material_index(),
+            bounds: model.mesh.bounds(index_offset..index_offset + index_count),
+        });
+
+        Ok(())
+    }
+}
+
+impl Gltf {
+    /// Creates a model with no geometry to build parts of the document into.
+    ///
+    /// The material table holds every material of the document, so that
+    /// primitive material indices can be used as material ids.
+    fn empty_model<V,I>(&self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index {
+        Ok(Model {
+            mesh: TriangularMesh::empty(),
+            materials: self.material_table()?,
+            submeshes: Vec::new(),
+        })
+    }
+
+    fn material_table(&self) -> Result<Vec<::Material>, Error> {
+        self.document.materials().map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let [r, g, b, a] = pbr.base_color_factor();
+            let [er, eg, eb] = material.emissive_factor();
+            let info = |info: Option<gltf::texture::Info>| info.map(|info| self.texture(info.texture(), info.tex_coord())).transpose();
+            let metallic_roughness = info(pbr.metallic_roughness_texture())?;
+
+            Ok(::Material {
+                name: material.name().unwrap_or("").to_owned(),
+                base_color: ::Color(r, g, b),
+                opacity: a,
+                emissive: ::Color(er, eg, eb),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                alpha_mode: match material.alpha_mode() {
+                    gltf::material::AlphaMode::Opaque => ::AlphaMode::Opaque,
+                    gltf::material::AlphaMode::Mask => ::AlphaMode::Mask { cutoff: material.alpha_cutoff().unwrap_or(0.5) },
+                    gltf::material::AlphaMode::Blend => ::AlphaMode::Blend,
+                },
+                double_sided: material.double_sided(),
+                base_color_texture: info(pbr.base_color_texture())?,
+                opacity_texture: None,
+                emissive_texture: info(material.emissive_texture())?,
+                metallic_texture: metallic_roughness.clone(),
+                roughness_texture: metallic_roughness,
+                normal_texture: material.normal_texture().map(|t| self.texture(t.texture(), t.tex_coord())).transpose()?,
+                occlusion_texture: material.occlusion_texture().map(|t| self.texture(t.texture(), t.tex_coord())).transpose()?,
+            })
+        }).collect()
+    }
+
+    fn texture(&self, texture: gltf::Texture, texture_coords: u32) -> Result<::Texture, Error> {
+        let source = match texture.source().source() {
+            gltf::image::Source::View { view, mime_type } => {
+                let start = view.offset();
+                let data = self.buffers.get(view.buffer().index())
+                    .and_then(|buffer| buffer.get(start..start.checked_add(view.length())?))
+                    .ok_or_else(|| invalid(format!("image buffer view {} is out of range", view.index())))?;
+                ::ImageSource::Embedded {
+                    mime_type: mime_type.to_owned(),
+                    data: data.to_vec(),
+                }
+            },
+            gltf::image::Source::Uri { uri, .. } => match self.directory {
+                Some(ref directory) if !uri.starts_with("data:") => ::ImageSource::Path(directory.join(uri)),
+                _ => ::ImageSource::Uri(uri.to_owned()),
+            },
+        };
+
+        Ok(::Texture { source, texture_coords })
+    }
+}
+
+/// Appends the world-space geometry of a node and its descendants.
+///
+/// `ancestors` holds the indices of the nodes above this one.
+fn build_node<V,I>(node: &Node, parent: Matrix4, model: &mut Model<V,I>, ancestors: &mut Vec<usize>) -> Result<(), Error>
+    where V: ::Vertex, I: Index, V: From<Vertex> {
+    if ancestors.contains(&node.index()) {
+        return Err(invalid(format!("node {} is its own ancestor", node.index())));
+    }
+    let world = parent.multiply(&Matrix4(node.matrix()));
+
+    // Mirroring transforms would otherwise turn the mesh inside out and
+    // flip the bitangents.
+    let mirrored = world.determinant() < 0.0;
+
+    if let Some(mesh) = node.mesh() {
+        let name = node.name().or_else(|| mesh.name()).unwrap_or("");
+
+        for primitive in mesh.primitives().filter(Primitive::is_triangles) {
+            let first_index = model.mesh.indices.len();
+            primitive.append_to(model, name, |mut vertex| {
+                vertex.position = world.transform_point(vertex.position);
+                vertex.normal = vertex.normal.map(|n| world.transform_normal(n));
+                vertex.tangent = vertex.tangent.map(|t| {
+                    let Vector(x, y, z) = world.transform_vector(Vector(t[0], t[1], t[2])).normalize();
+                    [x, y, z, if mirrored { -t[3] } else { t[3] }]
+                });
+                vertex
+            })?;
+
+            if mirrored {
+                let indices = first_index..model.mesh.indices.len();
+                model.mesh.flip_winding(indices);
+            }
+        }
+    }
+
+    ancestors.push(node.index());
+    for child in node.children() {
+        build_node(&child, world, model, ancestors)?;
+    }
+    ancestors.pop();
+
+    Ok(())
+}
+
+impl From<Vertex> for Vector {
+    fn from(v: Vertex) -> Vector {
+        v.position
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    ErrorKind::InvalidGltf(reason).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Model, Vector};
+
+    fn load_all() -> Vec<Gltf> {
+        ["res/cube.gltf", "res/cube-embedded.gltf", "res/cube.glb"].iter().map(|path| {
+            from_path(path).unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn can_enumerate_meshes() {
+        for gltf in load_all() {
+            let meshes: Vec<_> = gltf.meshes().collect();
+            assert_eq!(meshes.len(), 1);
+            assert_eq!(meshes[0].name(), Some("Cube"));
+            assert_eq!(meshes[0].primitives().count(), 1);
+        }
+    }
+
+    #[test]
+    fn can_read_attributes() {
+        for gltf in load_all() {
+            let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+
+            assert_eq!(primitive.attributes(), vec![Attribute::Position, Attribute::Normal, Attribute::TexCoords(0)]);
+            assert_eq!(primitive.material_index(), Some(0));
+            assert_eq!(primitive.positions().unwrap()[0], Vector(1.0, -1.0, 1.0));
+            assert_eq!(primitive.normals().unwrap()[0], Vector(1.0, 0.0, 0.0));
+            assert_eq!(primitive.texture_coords(0).unwrap()[2], [1.0, 0.0]);
+            assert_eq!(primitive.texture_coords(1), None);
+            assert_eq!(primitive.tangents(), None);
+            assert_eq!(primitive.indices().unwrap().len(), 36);
+        }
+    }
+
+    #[test]
+    fn can_walk_node_tree() {
+        let gltf = from_path("res/cube.glb").unwrap();
+        let roots = gltf.root_nodes();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].name(), Some("Root"));
+        assert!(roots[0].mesh().is_none());
+
+        let children: Vec<_> = roots[0].children().collect();
+        assert_eq!(children.iter().map(|c| c.name().unwrap()).collect::<Vec<_>>(), vec!["Crate", "Stacked crate"]);
+        assert_eq!(children[0].decomposed().0, [2.0, 0.0, 0.0]);
+        assert_eq!(children[0].matrix()[3], [2.0, 0.0, 0.0, 1.0]);
+        assert_eq!(children[1].mesh().unwrap().index(), 0);
+    }
+
+    #[test]
+    fn can_build_primitive() {
+        let gltf = from_path("res/cube.gltf").unwrap();
+        let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+        let cube: Model<Vector, u16> = Model::new(primitive).unwrap();
+
+        assert_eq!(cube.mesh.vertices.len(), 24);
+        assert_eq!(cube.mesh.triangles().count(), 12);
+    }
+
+    #[test]
+    fn can_build_mesh() {
+        let gltf = from_path("res/cube-embedded.gltf").unwrap();
+        let cube: Model<Vector, u16> = Model::new(gltf.meshes().next().unwrap()).unwrap();
+
+        assert_eq!(cube.mesh.indices.len(), 36);
+    }
+
+    /// Loads `res/cube.gltf` after making changes to its JSON.
+    fn load_modified<F>(modify: F) -> Result<Gltf, Error>
+        where F: FnOnce(&mut gltf::json::Value) {
+        let mut json: gltf::json::Value = gltf::json::deserialize::from_reader(File::open("res/cube.gltf").unwrap()).unwrap();
+        modify(&mut json);
+        let bytes = gltf::json::serialize::to_vec(&json).unwrap();
+        from_memory(&mut &bytes[..], |uri| File::open(Path::new("res").join(uri)))
+    }
+
+    #[test]
+    fn rejects_short_attributes() {
+        let gltf = load_modified(|json| json["accessors"][2]["count"] = 12.into()).unwrap();
+        let primitive = gltf.meshes().next().unwrap().primitives().next().unwrap();
+
+        match primitive.vertices() {
+            Err(Error(ErrorKind::InvalidGltf(..), _)) => (),
+            result => panic!("expected an invalid file but got {:?}", result.map(|v| v.len())),
+        }
+        assert!(Model::<Vector, u16>::new(gltf).is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_images() {
+        let gltf = load_modified(|json| {
+            let value = |text: &str| gltf::json::deserialize::from_str(text).unwrap();
+            json["bufferViews"].as_array_mut().unwrap().push(value(r#"{ "buffer": 0, "byteOffset": 820, "byteLength": 40 }"#));
+            json["images"][0] = value(r#"{ "bufferView": 4, "mimeType": "image/png" }"#);
+        });
+
+        match gltf.and_then(Model::<Vector, u16>::new) {
+            Err(Error(ErrorKind::InvalidGltf(..), _)) => (),
+            result => panic!("expected an invalid file but got {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn rejects_node_cycles() {
+        let gltf = load_modified(|json| json["nodes"][1]["children"] = gltf::json::deserialize::from_str("[0]").unwrap()).unwrap();
+
+        match Model::<Vector, u16>::new(gltf) {
+            Err(Error(ErrorKind::InvalidGltf(..), _)) => (),
+            result => panic!("expected an invalid file but got {:?}", result.map(|_| ())),
+        }
+        let gltf = load_modified(|json| json["nodes"][1]["children"] = gltf::json::deserialize::from_str("[0]").unwrap()).unwrap();
+        assert!(Scene::<Vector, u16>::new(gltf).unwrap().flatten().is_err());
+    }
+
+    #[test]
+    fn reports_missing_external_buffer() {
+        let mut file = File::open("res/cube.gltf").unwrap();
+        let result = from_memory(&mut file, |_| File::open("res/does-not-exist.bin"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn builds_material_table() {
+        let gltf = from_path("res/cube.gltf").unwrap();
+        let model: Model<Vector, u16> = Model::new(gltf).unwrap();
+
+        assert_eq!(model.materials.len(), 1);
+        let wood = &model.materials[0];
+        assert_eq!(wood.name, "Wood");
+        assert_eq!(wood.base_color, ::Color(0.8, 0.6, 0.4));
+        assert_eq!((wood.metallic, wood.roughness, wood.alpha_mode), (0.0, 0.9, ::AlphaMode::Opaque));
+        assert_eq!(wood.base_color_texture, Some(::Texture::from_path(Path::new("res").join("textures/wood.png"))));
+        assert_eq!(wood.normal_texture, None);
+    }
+
+    #[test]
+    fn records_submesh_per_instance() {
+        let scene: Model<Vector, u16> = Model::new(from_path("res/cube.glb").unwrap()).unwrap();
+        let names: Vec<_> = scene.submeshes.iter().map(|s| (s.name.as_str(), s.index_offset, s.index_count, s.material)).collect();
+        assert_eq!(names, vec![("Crate", 0, 36, Some(0)), ("Stacked crate", 36, 36, Some(0))]);
+
+        assert_eq!(scene.submeshes[0].bounds, ::Bounds { min: Vector(1.0, -1.0, -1.0), max: Vector(3.0, 1.0, 1.0) });
+    }
+
+    #[test]
+    fn builds_scene_hierarchy() {
+        let scene: Scene<Vector, u16> = Scene::new(from_path("res/cube.gltf").unwrap()).unwrap();
+
+        assert_eq!(scene.models.len(), 1);
+        assert!(scene.models[0].materials.is_empty());
+        assert_eq!(scene.materials[0].name, "Wood");
+
+        let root = &scene.nodes[scene.roots[0]];
+        assert_eq!(root.name, "Root");
+        let children: Vec<_> = root.children.iter().map(|&c| (scene.nodes[c].name.as_str(), scene.nodes[c].model)).collect();
+        assert_eq!(children, vec![("Crate", Some(0)), ("Stacked crate", Some(0))]);
+
+        let flattened = scene.flatten().unwrap();
+        let built: Model<Vector, u16> = Model::new(from_path("res/cube.gltf").unwrap()).unwrap();
+        assert_eq!(flattened, built);
+    }
+}
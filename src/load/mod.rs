@@ -1,5 +1,515 @@
 //! Loaders for the different mesh formats.
+//!
+//! Each format lives in its own module and is enabled by the cargo feature
+//! of the same name. If the format of a file is not known ahead of time,
+//! `from_path` and `from_reader` will detect it and hand back a `LoadedFile`.
+
+use {Model, BuildModel, Scene, BuildScene, TransformVertex, Vector, Matrix4, Color, Index, Error, ErrorKind};
+use {HasNormal, HasTexCoords, HasColor, HasTangent};
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
 
 #[cfg(feature = "wavefront")]
 pub mod wavefront;
 
+#[cfg(feature = "stl")]
+pub mod stl;
+
+#[cfg(feature = "ply")]
+pub mod ply;
+
+#[cfg(feature = "gltf")]
+pub mod gltf;
+
+/// A model file format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Wavefront `.obj`.
+    Wavefront,
+    /// STL, in either its ASCII or binary flavour.
+    Stl,
+    /// Stanford `.ply`.
+    Ply,
+    /// glTF 2.0, as either `.gltf` or `.glb`.
+    Gltf,
+}
+
+/// A file loaded by one of the format-specific loaders.
+#[allow(clippy::large_enum_variant)]
+pub enum LoadedFile {
+    #[cfg(feature = "wavefront")]
+    Wavefront(wavefront::Wavefront),
+    #[cfg(feature = "stl")]
+    Stl(stl::Stl),
+    #[cfg(feature = "ply")]
+    Ply(ply::Ply),
+    #[cfg(feature = "gltf")]
+    Gltf(gltf::Gltf),
+}
+
+/// A vertex, holding the attributes common to every format.
+///
+/// Attributes the file did not provide are `None`, so that passes can fill
+/// them in.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Vertex {
+    pub position: Vector,
+    pub normal: Option<Vector>,
+    pub texture_coords: Option<[f32; 2]>,
+    pub color: Option<Color>,
+    /// The tangent, with the bitangent sign stored in `w`.
+    pub tangent: Option<[f32; 4]>,
+}
+
+/// Loads a model file of any supported format from disk.
+///
+/// The format is detected from the file contents, falling back to the file
+/// extension when the contents are ambiguous.
+pub fn from_path<S>(path: S) -> Result<LoadedFile, Error>
+    where S: AsRef<Path> {
+    let path = path.as_ref();
+
+    let mut prefix = Vec::new();
+    File::open(path)?.take(SNIFF_LENGTH).read_to_end(&mut prefix)?;
+    let length = path.metadata()?.len();
+
+    match Format::detect(Some(path), &prefix, Some(length))? {
+        #[cfg(feature = "wavefront")]
+        Format::Wavefront => Ok(LoadedFile::Wavefront(wavefront::from_path(path)?)),
+        #[cfg(feature = "stl")]
+        Format::Stl => Ok(LoadedFile::Stl(stl::from_path(path)?)),
+        #[cfg(feature = "ply")]
+        Format::Ply => Ok(LoadedFile::Ply(ply::from_path(path)?)),
+        #[cfg(feature = "gltf")]
+        Format::Gltf => Ok(LoadedFile::Gltf(gltf::from_path(path)?)),
+        #[allow(unreachable_patterns)]
+        format => Err(format.not_enabled()),
+    }
+}
+
+/// Loads a model file of any supported format from a byte stream.
+///
+/// The format is detected from the file contents alone. Files that refer to
+/// other files, such as Wavefront material libraries or external glTF
+/// buffers, cannot have those references resolved.
+pub fn from_reader<R>(reader: &mut R) -> Result<LoadedFile, Error>
+    where R: Read {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    match Format::detect(None, &bytes, Some(bytes.len() as u64))? {
+        #[cfg(feature = "wavefront")]
+        Format::Wavefront => {
+            let no_materials = |_: &Path| ::std::io::Cursor::new(Vec::new());
+            Ok(LoadedFile::Wavefront(wavefront::from_memory(&mut ::std::io::Cursor::new(bytes), no_materials)?))
+        },
+        #[cfg(feature = "stl")]
+        Format::Stl => Ok(LoadedFile::Stl(stl::from_bytes(&bytes)?)),
+        #[cfg(feature = "ply")]
+        Format::Ply => Ok(LoadedFile::Ply(ply::from_bytes(&bytes)?)),
+        #[cfg(feature = "gltf")]
+        Format::Gltf => {
+            let no_buffers = |path: &Path| -> ::std::io::Result<::std::io::Cursor<Vec<u8>>> {
+                Err(::std::io::Error::new(::std::io::ErrorKind::NotFound,
+                                          format!("cannot load external buffer '{}'", path.display())))
+            };
+            Ok(LoadedFile::Gltf(gltf::from_memory(&mut ::std::io::Cursor::new(bytes), no_buffers)?))
+        },
+        #[allow(unreachable_patterns)]
+        format => Err(format.not_enabled()),
+    }
+}
+
+/// The number of bytes needed to detect the format of a file.
+const SNIFF_LENGTH: u64 = 512;
+
+impl Format {
+    /// Gets the format associated with a file extension.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension.to_lowercase().as_str() {
+            "obj" => Some(Format::Wavefront),
+            "stl" => Some(Format::Stl),
+            "ply" => Some(Format::Ply),
+            "gltf" | "glb" => Some(Format::Gltf),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the start of a file.
+    ///
+    /// `length` is the total size of the file, if known, which is used to
+    /// recognise binary STL files as they have no magic number.
+    pub fn from_magic(prefix: &[u8], length: Option<u64>) -> Option<Format> {
+        let text = prefix.iter().position(|b| !b.is_ascii_whitespace()).map(|i| &prefix[i..]).unwrap_or(&[]);
+
+        if prefix.starts_with(b"glTF") || text.starts_with(b"{") {
+            Some(Format::Gltf)
+        } else if prefix.starts_with(b"ply\n") || prefix.starts_with(b"ply\r\n") {
+            Some(Format::Ply)
+        } else if is_binary_stl(prefix, length) || text.starts_with(b"solid") {
+            Some(Format::Stl)
+        } else if looks_like_wavefront(text) {
+            Some(Format::Wavefront)
+        } else {
+            None
+        }
+    }
+
+    /// Detects the format of a file from its magic bytes and extension.
+    pub fn detect(path: Option<&Path>, prefix: &[u8], length: Option<u64>) -> Result<Format, Error> {
+        let extension = path.and_then(Path::extension).and_then(|ext| ext.to_str());
+
+        Format::from_magic(prefix, length)
+            .or_else(|| extension.and_then(Format::from_extension))
+            .ok_or_else(|| {
+                let reason = match extension {
+                    Some(extension) => format!("unrecognised file contents and unknown extension '.{}'", extension),
+                    None => "unrecognised file contents".to_owned(),
+                };
+                ErrorKind::UnknownModelFormat(reason).into()
+            })
+    }
+
+    /// Gets the name of the cargo feature that enables the format.
+    pub fn feature(&self) -> &'static str {
+        match *self {
+            Format::Wavefront => "wavefront",
+            Format::Stl => "stl",
+            Format::Ply => "ply",
+            Format::Gltf => "gltf",
+        }
+    }
+
+    /// Checks whether support for the format was compiled in.
+    pub fn is_enabled(&self) -> bool {
+        match *self {
+            Format::Wavefront => cfg!(feature = "wavefront"),
+            Format::Stl => cfg!(feature = "stl"),
+            Format::Ply => cfg!(feature = "ply"),
+            Format::Gltf => cfg!(feature = "gltf"),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn not_enabled(self) -> Error {
+        ErrorKind::FormatNotEnabled(format!("{:?}", self), self.feature().to_owned()).into()
+    }
+}
+
+impl LoadedFile {
+    /// Gets the format of the file.
+    pub fn format(&self) -> Format {
+        match *self {
+            #[cfg(feature = "wavefront")]
+            LoadedFile::Wavefront(..) => Format::Wavefront,
+            #[cfg(feature = "stl")]
+            LoadedFile::Stl(..) => Format::Stl,
+            #[cfg(feature = "ply")]
+            LoadedFile::Ply(..) => Format::Ply,
+            #[cfg(feature = "gltf")]
+            LoadedFile::Gltf(..) => Format::Gltf,
+        }
+    }
+}
+
+impl BuildModel for LoadedFile {
+    type Vertex = Vertex;
+
+    #[cfg(not(any(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf")))]
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        match self {}
+    }
+
+    #[cfg(any(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf"))]
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let model: Model<Generic<V>, I> = match self {
+            #[cfg(feature = "wavefront")]
+            LoadedFile::Wavefront(file) => file.build_model()?,
+            #[cfg(feature = "stl")]
+            LoadedFile::Stl(file) => file.build_model()?,
+            #[cfg(feature = "ply")]
+            LoadedFile::Ply(file) => file.build_model()?,
+            #[cfg(feature = "gltf")]
+            LoadedFile::Gltf(file) => file.build_model()?,
+        };
+
+        Ok(Generic::unwrap_model(model))
+    }
+}
+
+/// Loads glTF files with their node hierarchy, and every other format
+/// as a scene with a single node.
+impl BuildScene for LoadedFile {
+    type Vertex = Vertex;
+
+    fn build_scene<V,I>(self) -> Result<Scene<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        match self {
+            #[cfg(feature = "gltf")]
+            LoadedFile::Gltf(file) => {
+                let scene: Scene<Generic<V>, I> = file.build_scene()?;
+
+                Ok(Scene {
+                    models: scene.models.into_iter().map(Generic::unwrap_model).collect(),
+                    materials: scene.materials,
+                    nodes: scene.nodes,
+                    roots: scene.roots,
+                })
+            },
+            #[allow(unreachable_patterns)]
+            file => Ok(Scene::from_model(file.build_model()?)),
+        }
+    }
+}
+
+/// Wraps a user vertex so it can be built from any format's vertex type,
+/// by going through the format-independent `Vertex`.
+#[cfg(any(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf"))]
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+struct Generic<V>(V);
+
+#[cfg(any(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf"))]
+impl<V: ::Vertex> Generic<V> {
+    fn unwrap_model<I: Index>(model: Model<Generic<V>, I>) -> Model<V, I> {
+        Model {
+            mesh: ::TriangularMesh {
+                vertices: model.mesh.vertices.into_iter().map(|v| v.0).collect(),
+                indices: model.mesh.indices,
+            },
+            materials: model.materials,
+            submeshes: model.submeshes,
+        }
+    }
+}
+
+#[cfg(any(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf"))]
+impl<V: ::Vertex> ::Vertex for Generic<V> {
+    fn position(&self) -> Vector { self.0.position() }
+    fn normal(&self) -> Option<Vector> { self.0.normal() }
+    fn texture_coords(&self) -> Option<[f32; 2]> { self.0.texture_coords() }
+    fn color(&self) -> Option<Color> { self.0.color() }
+    fn tangent(&self) -> Option<[f32; 4]> { self.0.tangent() }
+}
+
+macro_rules! impl_generic_vertex {
+    ($feature:expr, $vertex:ty) => {
+        #[cfg(feature = $feature)]
+        impl<V: From<Vertex>> From<$vertex> for Generic<V> {
+            fn from(v: $vertex) -> Self { Generic(V::from(Vertex::from(v))) }
+        }
+    }
+}
+
+impl_generic_vertex!("wavefront", wavefront::Vertex);
+impl_generic_vertex!("stl", stl::Vertex);
+impl_generic_vertex!("ply", ply::Vertex);
+impl_generic_vertex!("gltf", gltf::Vertex);
+
+#[cfg(feature = "wavefront")]
+impl From<wavefront::Vertex> for Vertex {
+    fn from(v: wavefront::Vertex) -> Vertex {
+        Vertex {
+            position: v.position,
+            normal: v.normal,
+            texture_coords: v.texture_coords.map(|t| [t.u, t.v]),
+            color: v.color,
+            tangent: None,
+        }
+    }
+}
+
+#[cfg(feature = "stl")]
+impl From<stl::Vertex> for Vertex {
+    fn from(v: stl::Vertex) -> Vertex {
+        Vertex { position: v.position, normal: Some(v.normal), texture_coords: None, color: None, tangent: None }
+    }
+}
+
+#[cfg(feature = "ply")]
+impl From<ply::Vertex> for Vertex {
+    fn from(v: ply::Vertex) -> Vertex {
+        let texture_coords = match (v.properties.get_f32("u").or_else(|| v.properties.get_f32("s")),
+                                    v.properties.get_f32("v").or_else(|| v.properties.get_f32("t"))) {
+            (Some(u), Some(v)) => Some([u, v]),
+            _ => None,
+        };
+
+        Vertex { position: v.position, normal: v.normal, texture_coords, color: v.color, tangent: None }
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl From<gltf::Vertex> for Vertex {
+    fn from(v: gltf::Vertex) -> Vertex {
+        Vertex {
+            position: v.position,
+            normal: v.normal,
+            texture_coords: v.texture_coords.first().cloned(),
+            color: v.colors.first().map(|c| Color(c[0], c[1], c[2])),
+            tangent: v.tangent,
+        }
+    }
+}
+
+impl ::Vertex for Vertex {
+    fn position(&self) -> Vector { self.position }
+    fn normal(&self) -> Option<Vector> { self.normal }
+    fn texture_coords(&self) -> Option<[f32; 2]> { self.texture_coords }
+    fn color(&self) -> Option<Color> { self.color }
+    fn tangent(&self) -> Option<[f32; 4]> { self.tangent }
+}
+
+impl HasNormal for Vertex {
+    fn set_normal(&mut self, normal: Vector) { self.normal = Some(normal) }
+}
+
+impl HasTexCoords for Vertex {
+    fn set_texture_coords(&mut self, texture_coords: [f32; 2]) { self.texture_coords = Some(texture_coords) }
+}
+
+impl HasColor for Vertex {
+    fn set_color(&mut self, color: Color) { self.color = Some(color) }
+}
+
+impl HasTangent for Vertex {
+    fn set_tangent(&mut self, tangent: [f32; 4]) { self.tangent = Some(tangent) }
+}
+
+impl TransformVertex for Vertex {
+    fn transform(&self, matrix: &Matrix4) -> Self {
+        Vertex {
+            position: matrix.transform_point(self.position),
+            normal: self.normal.map(|n| matrix.transform_normal(n)),
+            tangent: self.tangent.map(|t| {
+                let Vector(x, y, z) = matrix.transform_vector(Vector(t[0], t[1], t[2])).normalize();
+                // Mirroring flips the bitangent relative to the normal and tangent.
+                [x, y, z, if matrix.determinant() < 0.0 { -t[3] } else { t[3] }]
+            }),
+            ..self.clone()
+        }
+    }
+}
+
+impl From<Vector> for Vertex {
+    fn from(position: Vector) -> Vertex {
+        Vertex { position, normal: None, texture_coords: None, color: None, tangent: None }
+    }
+}
+
+impl From<Vertex> for Vector {
+    fn from(v: Vertex) -> Vector {
+        v.position
+    }
+}
+
+fn is_binary_stl(prefix: &[u8], length: Option<u64>) -> bool {
+    match (prefix.get(80..84), length) {
+        (Some(count), Some(length)) => {
+            let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as u64;
+            84 + count * 50 == length
+        },
+        _ => false,
+    }
+}
+
+/// Checks whether the first statement of a text file is a Wavefront keyword.
+fn looks_like_wavefront(text: &[u8]) -> bool {
+    const KEYWORDS: &[&str] = &["v", "vn", "vt", "vp", "f", "l", "p", "o", "g", "s", "mtllib", "usemtl"];
+
+    let text = String::from_utf8_lossy(text);
+    let first_statement = text.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#'));
+
+    match first_statement.and_then(|line| line.split_whitespace().next()) {
+        Some(keyword) => KEYWORDS.contains(&keyword),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(all(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf"))]
+    #[test]
+    fn detects_format_of_every_fixture() {
+        let fixtures = [
+            ("res/cube.obj", Format::Wavefront),
+            ("res/world.obj", Format::Wavefront),
+            ("res/cube.stl", Format::Stl),
+            ("res/cube-binary.stl", Format::Stl),
+            ("res/cube.ply", Format::Ply),
+            ("res/cube-binary-be.ply", Format::Ply),
+            ("res/cube.gltf", Format::Gltf),
+            ("res/cube.glb", Format::Gltf),
+        ];
+
+        for &(path, format) in fixtures.iter() {
+            assert_eq!(from_path(path).unwrap().format(), format, "{}", path);
+        }
+    }
+
+    #[cfg(all(feature = "stl", feature = "gltf"))]
+    #[test]
+    fn detects_format_without_extension() {
+        let bytes = ::std::fs::read("res/cube-binary.stl").unwrap();
+        assert_eq!(from_reader(&mut &bytes[..]).unwrap().format(), Format::Stl);
+
+        let bytes = ::std::fs::read("res/cube.glb").unwrap();
+        assert_eq!(from_reader(&mut &bytes[..]).unwrap().format(), Format::Gltf);
+    }
+
+    #[test]
+    fn falls_back_to_extension() {
+        assert_eq!(Format::detect(Some(Path::new("mesh.OBJ")), b"", None).unwrap(), Format::Wavefront);
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        let error = from_reader(&mut &b"\x00\x01\x02 definitely not a mesh"[..]).err().unwrap();
+
+        match *error.kind() {
+            ErrorKind::UnknownModelFormat(..) => (),
+            ref kind => panic!("unexpected error: {}", kind),
+        }
+    }
+
+    #[cfg(all(feature = "wavefront", feature = "stl", feature = "ply"))]
+    #[test]
+    fn builds_every_format_into_the_same_cube() {
+        for path in ["res/cube.obj", "res/cube.stl", "res/cube.ply"].iter() {
+            let model: Model<Vector, u32> = Model::new(from_path(path).unwrap()).unwrap();
+            assert_eq!(model.mesh.triangles().count(), 12, "{}", path);
+        }
+    }
+
+    #[cfg(feature = "gltf")]
+    #[test]
+    fn gltf_is_built_in_world_space() {
+        let model: Model<Vertex, u32> = Model::new(from_path("res/cube.glb").unwrap()).unwrap();
+
+        // Two instances of the cube: one translated along x, one along y and shrunk.
+        assert_eq!(model.mesh.triangles().count(), 24);
+        assert_eq!(model.mesh.vertices[0].position, Vector(3.0, -1.0, 1.0));
+        assert_eq!(model.mesh.vertices[0].normal, Some(Vector(1.0, 0.0, 0.0)));
+
+        let max_y = model.mesh.vertices.iter().map(|v| v.position.1).fold(0.0, f32::max);
+        assert!((max_y - 2.5).abs() < 1e-5);
+    }
+
+    #[cfg(all(feature = "wavefront", feature = "stl", feature = "ply", feature = "gltf"))]
+    #[test]
+    fn loads_every_format_as_a_scene() {
+        for path in ["res/cube.obj", "res/cube.stl", "res/cube.ply"].iter() {
+            let scene: Scene<Vertex, u32> = Scene::new(from_path(path).unwrap()).unwrap();
+            assert_eq!((scene.models.len(), scene.nodes.len()), (1, 1), "{}", path);
+            assert_eq!(scene.flatten().unwrap().mesh.triangles().count(), 12, "{}", path);
+        }
+
+        let scene: Scene<Vertex, u32> = Scene::new(from_path("res/cube.glb").unwrap()).unwrap();
+        assert_eq!((scene.models.len(), scene.nodes.len(), scene.instances().unwrap().len()), (1, 3, 2));
+    }
+}
@@ -0,0 +1,692 @@
+//! Loader for the PLY (Stanford polygon) file format.
+//!
+//! ASCII, binary little-endian and binary big-endian files are supported.
+//!
+//! PLY files declare their own schema in the header - a list of elements,
+//! each with a list of typed properties. Every property of every element is
+//! kept, so that extra per-vertex data such as scanner confidence or
+//! curvature can be read back out of a `Vertex` via its property bag.
+
+use {Model, TriangularMesh, BuildModel, Vector, Color, Index, Error, ErrorKind};
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+use std::str;
+
+/// The name of the element that holds vertex data.
+pub const VERTEX_ELEMENT: &str = "vertex";
+/// The name of the element that holds polygon data.
+pub const FACE_ELEMENT: &str = "face";
+
+/// A PLY model.
+pub struct Ply {
+    format: Format,
+    comments: Vec<String>,
+    elements: Vec<ElementData>,
+}
+
+/// The encoding of the body of a PLY file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// The type of a single number stored in a PLY file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalarType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+/// The type of a property.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PropertyType {
+    /// A single number.
+    Scalar(ScalarType),
+    /// A variable-length list of numbers, prefixed with its length.
+    List { count: ScalarType, item: ScalarType },
+}
+
+/// The declaration of a property in the header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyDefinition {
+    pub name: String,
+    pub ty: PropertyType,
+}
+
+/// The value of a property.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Char(i8),
+    UChar(u8),
+    Short(i16),
+    UShort(u16),
+    Int(i32),
+    UInt(u32),
+    Float(f32),
+    Double(f64),
+    List(Vec<Value>),
+}
+
+/// The values of every property of a single element instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    definitions: Rc<[PropertyDefinition]>,
+    values: Vec<Value>,
+}
+
+/// A vertex.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: Vector,
+    /// The normal, built from the `nx`, `ny` and `nz` properties.
+    pub normal: Option<Vector>,
+    /// The color, built from the `red`, `green` and `blue` properties.
+    ///
+    /// Integer channels are normalized into the `0..1` range.
+    pub color: Option<Color>,
+    /// Every property of the vertex, including the ones above.
+    pub properties: Record,
+}
+
+/// An element declared in a PLY file, such as `vertex` or `face`.
+pub struct Element<'a> {
+    ply: &'a Ply,
+    data: &'a ElementData,
+}
+
+/// An iterator over all elements in a file.
+pub struct Elements<'a> {
+    ply: &'a Ply,
+    elements: ::std::slice::Iter<'a, ElementData>,
+}
+
+struct ElementData {
+    name: String,
+    definitions: Rc<[PropertyDefinition]>,
+    records: Vec<Vec<Value>>,
+}
+
+/// Loads a PLY file from disk.
+pub fn from_path<S>(path: S) -> Result<Ply, Error>
+    where S: AsRef<Path> {
+    let mut file = File::open(path.as_ref())?;
+    from_memory(&mut file)
+}
+
+/// Loads a PLY file from a byte stream.
+pub fn from_memory<R>(reader: &mut R) -> Result<Ply, Error>
+    where R: Read {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    from_bytes(&bytes)
+}
+
+/// Loads a PLY file from a byte slice.
+pub fn from_bytes(bytes: &[u8]) -> Result<Ply, Error> {
+    let (header, body) = parse_header(bytes)?;
+
+    let elements = match header.format {
+        Format::Ascii => {
+            let text = str::from_utf8(body).map_err(|e| invalid(format!("ASCII body is not valid UTF-8: {}", e)))?;
+            let mut words = text.split_whitespace();
+            read_elements(header.elements, |ty| read_ascii_scalar(&mut words, ty))?
+        },
+        Format::BinaryLittleEndian | Format::BinaryBigEndian => {
+            let mut reader = BinaryReader {
+                bytes: body,
+                position: 0,
+                big_endian: header.format == Format::BinaryBigEndian,
+            };
+            read_elements(header.elements, |ty| reader.read_scalar(ty))?
+        },
+    };
+
+    Ok(Ply { format: header.format, comments: header.comments, elements })
+}
+
+impl Ply {
+    /// Gets the encoding of the file.
+    pub fn format(&self) -> Format { self.format }
+
+    /// Gets the `comment` and `obj_info` lines from the header.
+    pub fn comments(&self) -> &[String] { &self.comments }
+
+    /// All of the elements declared in the file, in header order.
+    pub fn elements(&self) -> Elements<'_> {
+        Elements { ply: self, elements: self.elements.iter() }
+    }
+
+    /// Finds an element by name.
+    pub fn element(&self, name: &str) -> Option<Element<'_>> {
+        self.elements().find(|e| e.name() == name)
+    }
+
+    /// Gets all of the vertices in the file.
+    pub fn vertices(&self) -> Result<Vec<Vertex>, Error> {
+        match self.element(VERTEX_ELEMENT) {
+            Some(element) => element.records().map(Vertex::from_record).collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Gets the triangulated vertex indices of every face in the file.
+    fn triangle_indices<I: Index>(&self, vertex_count: usize) -> Result<Vec<I>, Error> {
+        let faces = match self.element(FACE_ELEMENT) {
+            Some(faces) => faces,
+            None => return Ok(Vec::new()),
+        };
+
+        let list_index = faces.data.definitions.iter().position(|def| {
+            def.name == "vertex_indices" || def.name == "vertex_index"
+        }).ok_or_else(|| invalid("face element has no 'vertex_indices' property".to_owned()))?;
+
+        let mut indices = Vec::new();
+        for record in faces.data.records.iter() {
+            let polygon = record[list_index].as_list().unwrap_or(&[]);
+
+            let polygon: Result<Vec<u64>, Error> = polygon.iter().map(|value| {
+                match value.as_u64() {
+                    Some(index) if (index as usize) < vertex_count => Ok(index),
+                    _ => Err(invalid(format!("face refers to invalid vertex {:?}", value))),
+                }
+            }).collect();
+            let polygon = polygon?;
+
+            // Triangulate as a fan around the first corner.
+            for i in 1..polygon.len().saturating_sub(1) {
+                for &index in [polygon[0], polygon[i], polygon[i + 1]].iter() {
+                    indices.push(I::from_u64(index)?);
+                }
+            }
+        }
+
+        Ok(indices)
+    }
+}
+
+impl<'a> Element<'a> {
+    /// Gets the name of the element.
+    pub fn name(&self) -> &str { &self.data.name }
+
+    /// Gets the declarations of the properties of the element.
+    pub fn properties(&self) -> &[PropertyDefinition] { &self.data.definitions }
+
+    /// Gets the number of instances of the element.
+    pub fn len(&self) -> usize { self.data.records.len() }
+
+    /// Checks if there are no instances of the element.
+    pub fn is_empty(&self) -> bool { self.data.records.is_empty() }
+
+    /// Gets every instance of the element.
+    pub fn records(&self) -> impl Iterator<Item=Record> + 'a {
+        let definitions = self.data.definitions.clone();
+        self.data.records.iter().map(move |values| {
+            Record { definitions: definitions.clone(), values: values.clone() }
+        })
+    }
+}
+
+impl Record {
+    /// Gets the declarations of the properties.
+    pub fn definitions(&self) -> &[PropertyDefinition] { &self.definitions }
+
+    /// Gets the value of a property by name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.definitions.iter().position(|def| def.name == name).map(|i| &self.values[i])
+    }
+
+    /// Gets the value of a scalar property as a float.
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        self.get(name).and_then(Value::as_f64).map(|v| v as f32)
+    }
+
+    /// Iterates over the `(name, value)` pairs of every property.
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &Value)> {
+        self.definitions.iter().map(|def| def.name.as_str()).zip(self.values.iter())
+    }
+
+    fn get_vector(&self, x: &str, y: &str, z: &str) -> Option<Vector> {
+        match (self.get_f32(x), self.get_f32(y), self.get_f32(z)) {
+            (Some(x), Some(y), Some(z)) => Some(Vector(x, y, z)),
+            _ => None,
+        }
+    }
+
+    fn get_color(&self, r: &str, g: &str, b: &str) -> Option<Color> {
+        let channel = |name| self.get(name).and_then(Value::as_normalized);
+
+        match (channel(r), channel(g), channel(b)) {
+            (Some(r), Some(g), Some(b)) => Some(Color(r, g, b)),
+            _ => None,
+        }
+    }
+}
+
+impl Value {
+    /// Gets the value of a scalar as a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Char(v) => Some(v as f64),
+            Value::UChar(v) => Some(v as f64),
+            Value::Short(v) => Some(v as f64),
+            Value::UShort(v) => Some(v as f64),
+            Value::Int(v) => Some(v as f64),
+            Value::UInt(v) => Some(v as f64),
+            Value::Float(v) => Some(v as f64),
+            Value::Double(v) => Some(v),
+            Value::List(..) => None,
+        }
+    }
+
+    /// Gets the value of a non-negative integer scalar.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Char(v) if v >= 0 => Some(v as u64),
+            Value::UChar(v) => Some(v as u64),
+            Value::Short(v) if v >= 0 => Some(v as u64),
+            Value::UShort(v) => Some(v as u64),
+            Value::Int(v) if v >= 0 => Some(v as u64),
+            Value::UInt(v) => Some(v as u64),
+            _ => None,
+        }
+    }
+
+    /// Gets the value of a scalar, mapping unsigned integers into `0..1`.
+    ///
+    /// Floats are returned as-is.
+    pub fn as_normalized(&self) -> Option<f32> {
+        match *self {
+            Value::UChar(v) => Some(v as f32 / u8::MAX as f32),
+            Value::UShort(v) => Some(v as f32 / u16::MAX as f32),
+            Value::UInt(v) => Some((v as f64 / u32::MAX as f64) as f32),
+            Value::Float(v) => Some(v),
+            Value::Double(v) => Some(v as f32),
+            _ => None,
+        }
+    }
+
+    /// Gets the items of a list.
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match *self {
+            Value::List(ref items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl Vertex {
+    /// Builds a vertex out of an instance of the `vertex` element.
+    pub fn from_record(properties: Record) -> Result<Vertex, Error> {
+        let position = properties.get_vector("x", "y", "z")
+            .ok_or_else(|| invalid("vertex is missing one of the 'x', 'y' or 'z' properties".to_owned()))?;
+        let normal = properties.get_vector("nx", "ny", "nz");
+        let color = properties.get_color("red", "green", "blue")
+            .or_else(|| properties.get_color("diffuse_red", "diffuse_green", "diffuse_blue"));
+
+        Ok(Vertex { position, normal, color, properties })
+    }
+}
+
+impl BuildModel for Ply
+{
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let vertices = self.vertices()?;
+        let indices = self.triangle_indices(vertices.len())?;
+
+        Ok(Model::from_mesh(TriangularMesh {
+            vertices: vertices.into_iter().map(V::from).collect(),
+            indices,
+        }))
+    }
+}
+
+/// Builds a model out of the instances of a single element.
+///
+/// Every instance becomes a vertex. The `vertex` element is triangulated
+/// using the `face` element; any other element is treated as a point cloud
+/// and produces an empty index list.
+impl<'a> BuildModel for Element<'a> {
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let vertices: Result<Vec<_>, _> = self.records().map(Vertex::from_record).collect();
+        let vertices = vertices?;
+
+        let indices = if self.name() == VERTEX_ELEMENT {
+            self.ply.triangle_indices(vertices.len())?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Model::from_mesh(TriangularMesh {
+            vertices: vertices.into_iter().map(V::from).collect(),
+            indices,
+        }))
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Element<'a>;
+
+    fn next(&mut self) -> Option<Element<'a>> {
+        self.elements.next().map(|data| Element { ply: self.ply, data })
+    }
+}
+
+impl From<Vertex> for Vector {
+    fn from(v: Vertex) -> Vector {
+        v.position
+    }
+}
+
+struct Header {
+    format: Format,
+    comments: Vec<String>,
+    elements: Vec<(String, usize, Vec<PropertyDefinition>)>,
+}
+
+/// Splits a file into its parsed header and the raw body.
+fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8]), Error> {
+    let mut position = 0;
+    let mut line_number = 0;
+    let mut format = None;
+    let mut comments = Vec::new();
+    let mut elements: Vec<(String, usize, Vec<PropertyDefinition>)> = Vec::new();
+
+    loop {
+        let line_end = bytes[position..].iter().position(|&b| b == b'\n')
+            .ok_or_else(|| invalid("header is missing 'end_header'".to_owned()))?;
+        let line = &bytes[position..position + line_end];
+        position += line_end + 1;
+        line_number += 1;
+
+        let line = str::from_utf8(line).map_err(|_| invalid(format!("header line {} is not valid UTF-8", line_number)))?;
+        let error = |reason: &str| invalid(format!("header line {}: {}", line_number, reason));
+        let words: Vec<_> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if words != ["ply"] { return Err(invalid("file does not start with 'ply'".to_owned())) }
+            continue;
+        }
+
+        match words.first() {
+            Some(&"format") => {
+                format = Some(match words.get(1) {
+                    Some(&"ascii") => Format::Ascii,
+                    Some(&"binary_little_endian") => Format::BinaryLittleEndian,
+                    Some(&"binary_big_endian") => Format::BinaryBigEndian,
+                    _ => return Err(error("unknown format")),
+                });
+            },
+            Some(&"comment") | Some(&"obj_info") => {
+                comments.push(line.trim_start()[words[0].len()..].trim().to_owned());
+            },
+            Some(&"element") => {
+                let count = words.get(2).and_then(|count| count.parse().ok())
+                    .ok_or_else(|| error("expected 'element <name> <count>'"))?;
+                elements.push((words[1].to_owned(), count, Vec::new()));
+            },
+            Some(&"property") => {
+                let properties = match elements.last_mut() {
+                    Some(element) => &mut element.2,
+                    None => return Err(error("property declared before any element")),
+                };
+
+                let (ty, name) = match words.len() {
+                    3 => (PropertyType::Scalar(parse_scalar_type(words[1]).ok_or_else(|| error("unknown property type"))?), words[2]),
+                    5 if words[1] == "list" => {
+                        let count = parse_scalar_type(words[2]).ok_or_else(|| error("unknown list count type"))?;
+                        let item = parse_scalar_type(words[3]).ok_or_else(|| error("unknown list item type"))?;
+                        (PropertyType::List { count, item }, words[4])
+                    },
+                    _ => return Err(error("malformed property declaration")),
+                };
+                properties.push(PropertyDefinition { name: name.to_owned(), ty });
+            },
+            Some(&"end_header") => break,
+            None => (),
+            Some(keyword) => return Err(error(&format!("unknown keyword '{}'", keyword))),
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid("header does not declare a format".to_owned()))?;
+    Ok((Header { format, comments, elements }, &bytes[position..]))
+}
+
+fn parse_scalar_type(name: &str) -> Option<ScalarType> {
+    Some(match name {
+        "char" | "int8" => ScalarType::Char,
+        "uchar" | "uint8" => ScalarType::UChar,
+        "short" | "int16" => ScalarType::Short,
+        "ushort" | "uint16" => ScalarType::UShort,
+        "int" | "int32" => ScalarType::Int,
+        "uint" | "uint32" => ScalarType::UInt,
+        "float" | "float32" => ScalarType::Float,
+        "double" | "float64" => ScalarType::Double,
+        _ => return None,
+    })
+}
+
+/// The most records to make room for before reading them.
+const MAX_PREALLOCATED_RECORDS: usize = 1 << 16;
+
+/// Reads the body of a file, one scalar at a time.
+fn read_elements<F>(declarations: Vec<(String, usize, Vec<PropertyDefinition>)>,
+                    mut read_scalar: F) -> Result<Vec<ElementData>, Error>
+    where F: FnMut(ScalarType) -> Result<Value, Error> {
+    declarations.into_iter().map(|(name, count, definitions)| {
+        // Records without properties read nothing, so the count could never
+        // be checked against the length of the file.
+        if definitions.is_empty() && count > 0 {
+            return Err(invalid(format!("element '{}' has records but no properties", name)));
+        }
+
+        // The count comes from the header, so only trust it as far as the
+        // records are actually there.
+        let mut records = Vec::with_capacity(count.min(MAX_PREALLOCATED_RECORDS));
+
+        for _ in 0..count {
+            let record: Result<Vec<_>, Error> = definitions.iter().map(|def| {
+                match def.ty {
+                    PropertyType::Scalar(ty) => read_scalar(ty),
+                    PropertyType::List { count, item } => {
+                        let len = read_scalar(count)?.as_u64()
+                            .ok_or_else(|| invalid(format!("list '{}' has an invalid length", def.name)))?;
+                        let items: Result<Vec<_>, _> = (0..len).map(|_| read_scalar(item)).collect();
+                        Ok(Value::List(items?))
+                    },
+                }
+            }).collect();
+
+            records.push(record?);
+        }
+
+        Ok(ElementData { name, definitions: definitions.into(), records })
+    }).collect()
+}
+
+fn read_ascii_scalar<'a, W>(words: &mut W, ty: ScalarType) -> Result<Value, Error>
+    where W: Iterator<Item=&'a str> {
+    let word = words.next().ok_or_else(|| invalid("unexpected end of file".to_owned()))?;
+    let error = || invalid(format!("'{}' is not a valid {:?}", word, ty));
+
+    Ok(match ty {
+        ScalarType::Char => Value::Char(word.parse().map_err(|_| error())?),
+        ScalarType::UChar => Value::UChar(word.parse().map_err(|_| error())?),
+        ScalarType::Short => Value::Short(word.parse().map_err(|_| error())?),
+        ScalarType::UShort => Value::UShort(word.parse().map_err(|_| error())?),
+        ScalarType::Int => Value::Int(word.parse().map_err(|_| error())?),
+        ScalarType::UInt => Value::UInt(word.parse().map_err(|_| error())?),
+        ScalarType::Float => Value::Float(word.parse().map_err(|_| error())?),
+        ScalarType::Double => Value::Double(word.parse().map_err(|_| error())?),
+    })
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    big_endian: bool,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self.bytes.get(self.position..self.position + N)
+            .ok_or_else(|| invalid("unexpected end of file".to_owned()))?;
+        self.position += N;
+
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        if self.big_endian { array.reverse() }
+        Ok(array)
+    }
+
+    fn read_scalar(&mut self, ty: ScalarType) -> Result<Value, Error> {
+        Ok(match ty {
+            ScalarType::Char => Value::Char(i8::from_le_bytes(self.read()?)),
+            ScalarType::UChar => Value::UChar(u8::from_le_bytes(self.read()?)),
+            ScalarType::Short => Value::Short(i16::from_le_bytes(self.read()?)),
+            ScalarType::UShort => Value::UShort(u16::from_le_bytes(self.read()?)),
+            ScalarType::Int => Value::Int(i32::from_le_bytes(self.read()?)),
+            ScalarType::UInt => Value::UInt(u32::from_le_bytes(self.read()?)),
+            ScalarType::Float => Value::Float(f32::from_le_bytes(self.read()?)),
+            ScalarType::Double => Value::Double(f64::from_le_bytes(self.read()?)),
+        })
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    ErrorKind::InvalidPly(reason).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Model, Vector, Color};
+
+    #[derive(Clone, Debug, PartialEq, PartialOrd)]
+    struct ScanVertex {
+        position: Vector,
+        color: Color,
+        confidence: f32,
+    }
+
+    impl ::Vertex for ScanVertex {
+        fn position(&self) -> Vector { self.position }
+    }
+
+    impl From<Vertex> for ScanVertex {
+        fn from(v: Vertex) -> Self {
+            ScanVertex {
+                position: v.position,
+                color: v.color.unwrap(),
+                confidence: v.properties.get_f32("confidence").unwrap(),
+            }
+        }
+    }
+
+    fn load_all() -> Vec<Ply> {
+        ["res/cube.ply", "res/cube-binary-le.ply", "res/cube-binary-be.ply"].iter().map(|path| {
+            from_path(path).unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn can_read_header() {
+        let formats: Vec<_> = load_all().iter().map(Ply::format).collect();
+        assert_eq!(formats, vec![Format::Ascii, Format::BinaryLittleEndian, Format::BinaryBigEndian]);
+
+        let cube = from_path("res/cube.ply").unwrap();
+        assert_eq!(cube.comments(), &["mash test fixture".to_owned(), "unit cube".to_owned()]);
+
+        let names: Vec<_> = cube.elements().map(|e| (e.name().to_owned(), e.len())).collect();
+        assert_eq!(names, vec![("vertex".to_owned(), 8), ("face".to_owned(), 6)]);
+
+        let faces = cube.element("face").unwrap();
+        assert_eq!(faces.properties()[0].ty, PropertyType::List { count: ScalarType::UChar, item: ScalarType::Int });
+    }
+
+    #[test]
+    fn all_encodings_agree() {
+        let models: Vec<Model<ScanVertex, u16>> = load_all().into_iter().map(|ply| Model::new(ply).unwrap()).collect();
+
+        assert_eq!(models[0], models[1]);
+        assert_eq!(models[0], models[2]);
+    }
+
+    #[test]
+    fn exposes_extra_properties() {
+        let vertices = from_path("res/cube-binary-be.ply").unwrap().vertices().unwrap();
+        let vertex = &vertices[1];
+
+        assert_eq!(vertex.position, Vector(1.0, -1.0, 1.0));
+        assert_eq!(vertex.normal, None);
+        assert_eq!(vertex.color, Some(Color(0.0, 1.0, 0.0)));
+        assert_eq!(vertex.properties.get("green"), Some(&Value::UChar(255)));
+        assert_eq!(vertex.properties.get("confidence"), Some(&Value::Float(0.25)));
+        assert_eq!(vertex.properties.iter().count(), 7);
+    }
+
+    #[test]
+    fn can_build_file() {
+        let cube: Model<ScanVertex, u16> = Model::new(from_path("res/cube.ply").unwrap()).unwrap();
+        assert_eq!(cube.mesh.vertices.len(), 8);
+        assert_eq!(cube.mesh.triangles().count(), 12);
+        assert_eq!(cube.mesh.vertices[2].confidence, 1.0);
+    }
+
+    #[test]
+    fn can_build_element() {
+        let cube = from_path("res/cube.ply").unwrap();
+
+        let mesh: Model<Vector, u16> = Model::new(cube.element("vertex").unwrap()).unwrap();
+        assert_eq!(mesh.mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn rejects_out_of_range_faces() {
+        let text = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\n\
+                    element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n3 0 0 1\n";
+        let result: Result<Model<Vector, u16>, _> = Model::new(from_bytes(text.as_bytes()).unwrap());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let bytes = ::std::fs::read("res/cube-binary-le.ply").unwrap();
+        assert!(from_bytes(&bytes[0..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn rejects_counts_larger_than_the_file() {
+        let header = |format: &str| format!("ply\nformat {} 1.0\nelement vertex 4000000000000000000\n\
+                                             property float x\nproperty float y\nproperty float z\nend_header\n", format);
+
+        for format in &["ascii", "binary_little_endian"] {
+            let mut bytes = header(format).into_bytes();
+            bytes.extend_from_slice(if *format == "ascii" { b"0 0 0\n" } else { &[0; 12] });
+            match from_bytes(&bytes) {
+                Err(Error(ErrorKind::InvalidPly(..), _)) => (),
+                result => panic!("expected an invalid file but got {:?}", result.map(|_| ())),
+            }
+        }
+
+        let empty = "ply\nformat ascii 1.0\nelement marker 4000000000000000000\nend_header\n";
+        assert!(from_bytes(empty.as_bytes()).is_err());
+    }
+}
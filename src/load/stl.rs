@@ -0,0 +1,362 @@
+//! Loader for the STL (stereolithography) file format.
+//!
+//! Both the ASCII and the binary flavours of the format are supported.
+//! The flavour is detected automatically from the file contents.
+
+use {Model, TriangularMesh, BuildModel, Vector, Index, Error, ErrorKind};
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The size of the header at the start of a binary STL file.
+pub const HEADER_SIZE: usize = 80;
+
+/// The size of a single facet record in a binary STL file.
+const FACET_RECORD_SIZE: usize = 50;
+
+/// An STL model.
+pub struct Stl {
+    format: Format,
+    name: String,
+    header: Option<[u8; HEADER_SIZE]>,
+    facets: Vec<Facet>,
+}
+
+/// The flavour of an STL file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The human-readable `solid ... endsolid` format.
+    Ascii,
+    /// The compact binary format.
+    Binary,
+}
+
+/// A single triangle in an STL file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Facet {
+    /// The normal of the facet, as written in the file.
+    pub normal: Vector,
+    /// The corners of the facet, in counter-clockwise order.
+    pub vertices: [Vector; 3],
+    /// The "attribute byte count" field of a binary facet.
+    ///
+    /// The specification says this must be zero, but some tools
+    /// use it to store a 15-bit color. Always zero for ASCII files.
+    pub attribute: u16,
+}
+
+/// A vertex.
+pub struct Vertex {
+    pub position: Vector,
+    /// The normal of the facet that the vertex belongs to.
+    pub normal: Vector,
+}
+
+/// Loads an STL file from disk.
+pub fn from_path<S>(path: S) -> Result<Stl, Error>
+    where S: AsRef<Path> {
+    let mut file = File::open(path.as_ref())?;
+    from_memory(&mut file)
+}
+
+/// Loads an STL file from a byte stream.
+pub fn from_memory<R>(reader: &mut R) -> Result<Stl, Error>
+    where R: Read {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    from_bytes(&bytes)
+}
+
+/// Loads an STL file from a byte slice.
+pub fn from_bytes(bytes: &[u8]) -> Result<Stl, Error> {
+    match detect_format(bytes) {
+        Format::Binary => parse_binary(bytes),
+        Format::Ascii => parse_ascii(bytes),
+    }
+}
+
+/// Guesses the flavour of an STL file.
+///
+/// Plenty of binary files start their header with `solid`, so the
+/// facet count is checked against the file size before anything else.
+pub fn detect_format(bytes: &[u8]) -> Format {
+    if bytes.len() >= HEADER_SIZE + 4 {
+        let facet_count = read_u32(&bytes[HEADER_SIZE..]) as usize;
+        let expected_len = facet_count.checked_mul(FACET_RECORD_SIZE)
+            .and_then(|len| len.checked_add(HEADER_SIZE + 4));
+
+        if expected_len == Some(bytes.len()) {
+            return Format::Binary;
+        }
+    }
+
+    let text = bytes.iter().skip_while(|b| b.is_ascii_whitespace());
+    let is_ascii = text.clone().take(5).eq(b"solid".iter()) &&
+        bytes.iter().all(|b| b.is_ascii());
+
+    if is_ascii { Format::Ascii } else { Format::Binary }
+}
+
+impl Stl {
+    /// Gets the flavour of the file that was loaded.
+    pub fn format(&self) -> Format { self.format }
+
+    /// Gets the name of the model.
+    ///
+    /// For ASCII files this is the name given after `solid`. For binary
+    /// files it is the header text, stopping at the first NUL byte.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Gets the raw 80-byte header of a binary file.
+    pub fn header(&self) -> Option<&[u8; HEADER_SIZE]> { self.header.as_ref() }
+
+    /// Gets all of the facets in the file.
+    pub fn facets(&self) -> &[Facet] { &self.facets }
+}
+
+impl BuildModel for Stl
+{
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut vertices: Vec<V> = Vec::with_capacity(self.facets.len() * 3);
+        let mut indices = Vec::with_capacity(self.facets.len() * 3);
+
+        for facet in self.facets {
+            for &position in facet.vertices.iter() {
+                indices.push(I::from_u64(vertices.len() as u64)?);
+                vertices.push(V::from(Vertex { position, normal: facet.normal }));
+            }
+        }
+
+        Ok(Model::from_mesh(TriangularMesh {
+            vertices,
+            indices,
+        }))
+    }
+}
+
+impl From<Vertex> for Vector {
+    fn from(v: Vertex) -> Vector {
+        v.position
+    }
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Stl, Error> {
+    if bytes.len() < HEADER_SIZE + 4 {
+        return Err(ErrorKind::InvalidStl(format!(
+            "file is {} bytes long but a binary header is {} bytes", bytes.len(), HEADER_SIZE + 4)).into());
+    }
+
+    let mut header = [0; HEADER_SIZE];
+    header.copy_from_slice(&bytes[0..HEADER_SIZE]);
+
+    let facet_count = read_u32(&bytes[HEADER_SIZE..]) as usize;
+    let records = &bytes[HEADER_SIZE + 4..];
+
+    if records.len() / FACET_RECORD_SIZE < facet_count {
+        return Err(ErrorKind::InvalidStl(format!(
+            "header declares {} facets but the file only contains {}",
+            facet_count, records.len() / FACET_RECORD_SIZE)).into());
+    }
+
+    let facets = records.chunks(FACET_RECORD_SIZE).take(facet_count).map(|record| {
+        Facet {
+            normal: read_vector(&record[0..]),
+            vertices: [read_vector(&record[12..]), read_vector(&record[24..]), read_vector(&record[36..])],
+            attribute: u16::from_le_bytes([record[48], record[49]]),
+        }
+    }).collect();
+
+    let name_len = header.iter().position(|&b| b == 0).unwrap_or(HEADER_SIZE);
+    let name = String::from_utf8_lossy(&header[0..name_len]).trim().to_owned();
+
+    Ok(Stl { format: Format::Binary, name, header: Some(header), facets })
+}
+
+fn parse_ascii(bytes: &[u8]) -> Result<Stl, Error> {
+    let text = ::std::str::from_utf8(bytes).map_err(|e| {
+        Error::from(ErrorKind::InvalidStl(format!("ASCII file is not valid UTF-8: {}", e)))
+    })?;
+
+    let mut parser = AsciiParser { lines: text.lines().enumerate(), line_number: 0 };
+    let mut name = None;
+    let mut facets = Vec::new();
+
+    // Some exporters write several solids back-to-back into one file.
+    while let Some(words) = parser.next_line() {
+        let solid_name = match words.first() {
+            Some(&"solid") => words[1..].join(" "),
+            _ => return Err(parser.error(format!("expected 'solid' but found '{}'", words.join(" ")))),
+        };
+        name.get_or_insert(solid_name);
+
+        loop {
+            let words = parser.expect_line()?;
+
+            match words.first() {
+                Some(&"facet") => facets.push(parser.parse_facet(&words)?),
+                Some(&"endsolid") => break,
+                _ => return Err(parser.error(format!("expected 'facet' or 'endsolid' but found '{}'", words.join(" ")))),
+            }
+        }
+    }
+
+    Ok(Stl { format: Format::Ascii, name: name.unwrap_or_default(), header: None, facets })
+}
+
+struct AsciiParser<'a> {
+    lines: ::std::iter::Enumerate<::std::str::Lines<'a>>,
+    line_number: usize,
+}
+
+impl<'a> AsciiParser<'a> {
+    /// Gets the words on the next non-blank line.
+    fn next_line(&mut self) -> Option<Vec<&'a str>> {
+        for (number, line) in self.lines.by_ref() {
+            let words: Vec<_> = line.split_whitespace().collect();
+
+            if !words.is_empty() {
+                self.line_number = number + 1;
+                return Some(words);
+            }
+        }
+
+        None
+    }
+
+    fn expect_line(&mut self) -> Result<Vec<&'a str>, Error> {
+        match self.next_line() {
+            Some(words) => Ok(words),
+            None => Err(ErrorKind::InvalidStl("unexpected end of file".to_owned()).into()),
+        }
+    }
+
+    fn expect_keywords(&mut self, keywords: &[&str]) -> Result<Vec<&'a str>, Error> {
+        let words = self.expect_line()?;
+
+        if words.len() >= keywords.len() && words[0..keywords.len()] == *keywords {
+            Ok(words)
+        } else {
+            Err(self.error(format!("expected '{}' but found '{}'", keywords.join(" "), words.join(" "))))
+        }
+    }
+
+    fn parse_facet(&mut self, words: &[&str]) -> Result<Facet, Error> {
+        if words.get(1) != Some(&"normal") {
+            return Err(self.error("expected 'facet normal'".to_owned()));
+        }
+        let normal = self.parse_vector(&words[2..])?;
+
+        self.expect_keywords(&["outer", "loop"])?;
+
+        let mut vertices = [Vector(0.0, 0.0, 0.0); 3];
+        for vertex in vertices.iter_mut() {
+            let words = self.expect_keywords(&["vertex"])?;
+            *vertex = self.parse_vector(&words[1..])?;
+        }
+
+        self.expect_keywords(&["endloop"])?;
+        self.expect_keywords(&["endfacet"])?;
+
+        Ok(Facet { normal, vertices, attribute: 0 })
+    }
+
+    fn parse_vector(&self, words: &[&str]) -> Result<Vector, Error> {
+        if words.len() != 3 {
+            return Err(self.error(format!("expected 3 components but found {}", words.len())));
+        }
+
+        let mut components = [0.0; 3];
+        for (component, word) in components.iter_mut().zip(words) {
+            *component = word.parse().map_err(|_| self.error(format!("invalid number '{}'", word)))?;
+        }
+
+        Ok(Vector(components[0], components[1], components[2]))
+    }
+
+    fn error(&self, reason: String) -> Error {
+        ErrorKind::InvalidStl(format!("line {}: {}", self.line_number, reason)).into()
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn read_f32(bytes: &[u8]) -> f32 {
+    f32::from_bits(read_u32(bytes))
+}
+
+fn read_vector(bytes: &[u8]) -> Vector {
+    Vector(read_f32(&bytes[0..]), read_f32(&bytes[4..]), read_f32(&bytes[8..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Model, Vector};
+
+    fn ascii_cube() -> Stl { from_path("res/cube.stl").unwrap() }
+    fn binary_cube() -> Stl { from_path("res/cube-binary.stl").unwrap() }
+
+    #[test]
+    fn can_load_ascii() {
+        let cube = ascii_cube();
+        assert_eq!(cube.format(), Format::Ascii);
+        assert_eq!(cube.name(), "Cube");
+        assert!(cube.header().is_none());
+        assert_eq!(cube.facets().len(), 12);
+    }
+
+    #[test]
+    fn can_load_binary() {
+        let cube = binary_cube();
+        assert_eq!(cube.format(), Format::Binary);
+        assert_eq!(cube.name(), "Cube exported by mash test fixtures");
+        assert_eq!(&cube.header().unwrap()[0..4], b"Cube");
+        assert_eq!(cube.facets().len(), 12);
+    }
+
+    #[test]
+    fn ascii_and_binary_agree() {
+        assert_eq!(ascii_cube().facets(), binary_cube().facets());
+    }
+
+    #[test]
+    fn exposes_facet_normals() {
+        let facet = ascii_cube().facets()[0];
+        assert_eq!(facet.normal, Vector(0.0, -1.0, 0.0));
+        assert_eq!(facet.vertices[0], Vector(1.0, -1.0, 1.0));
+    }
+
+    #[test]
+    fn binary_header_starting_with_solid_is_binary() {
+        let mut bytes = ::std::fs::read("res/cube-binary.stl").unwrap();
+        bytes[0..5].copy_from_slice(b"solid");
+        assert_eq!(detect_format(&bytes), Format::Binary);
+    }
+
+    #[test]
+    fn reports_line_of_bad_ascii() {
+        let text = "solid x\nfacet normal 0 0 1\nouter loop\nvertex 0 0 zero\n";
+        let error = from_bytes(text.as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "invalid STL file: line 4: invalid number 'zero'");
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let bytes = ::std::fs::read("res/cube-binary.stl").unwrap();
+        assert!(from_bytes(&bytes[0..200]).is_err());
+    }
+
+    #[test]
+    fn can_build_file() {
+        let cube: Model<Vector, u16> = Model::new(binary_cube()).unwrap();
+        assert_eq!(cube.mesh.vertices.len(), 36);
+        assert_eq!(cube.mesh.triangles().count(), 12);
+    }
+}
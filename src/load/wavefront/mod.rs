@@ -0,0 +1,1108 @@
+//! Loader for the Wavefront `.obj` file format.
+
+use {Model, TriangularMesh, BuildModel, Scene, BuildScene, Vector, Color, Index, Error};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::ops::Range;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+pub use self::triangulate::Triangulation;
+
+mod parse;
+mod triangulate;
+
+/// A wavefront model.
+pub struct Wavefront {
+    positions: Vec<Vector>,
+    /// The color given after each position, if any.
+    colors: Vec<Option<Color>>,
+    texture_coords: Vec<TexCoord>,
+    normals: Vec<Vector>,
+    objects: Vec<ObjectData>,
+    materials: Vec<MaterialData>,
+    path: Option<PathBuf>,
+    /// The names given by each `g` statement, starting with no groups.
+    group_sets: Vec<Vec<String>>,
+    warnings: Vec<Warning>,
+    triangulation: Triangulation,
+}
+
+/// A vertex.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: Vector,
+    pub normal: Option<Vector>,
+    pub texture_coords: Option<TexCoord>,
+    /// The color given by the `v x y z r g b` extension, if any.
+    pub color: Option<Color>,
+}
+
+/// Texture coordinates, from a `vt` statement.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TexCoord {
+    pub u: f32,
+    /// The vertical coordinate, which is zero if the statement omits it.
+    pub v: f32,
+    /// The depth of a 3D texture, if the statement gives one.
+    pub w: Option<f32>,
+}
+
+/// A material.
+#[derive(Copy, Clone)]
+pub struct Material<'a> {
+    material: &'a MaterialData,
+}
+
+/// A named object in a Wavefront file.
+#[derive(Copy, Clone)]
+pub struct Object<'a> {
+    wavefront: &'a Wavefront,
+    object: &'a ObjectData,
+    triangulation: Triangulation,
+}
+
+/// A face of an object, as it was written in the file.
+#[derive(Copy, Clone)]
+pub struct Polygon<'a> {
+    wavefront: &'a Wavefront,
+    face: &'a Face,
+}
+
+/// A run of consecutive faces of an object that share a material.
+pub struct MaterialRange<'a> {
+    pub material: Option<Material<'a>>,
+    /// The indices of the faces, as returned by `Object::polygons`.
+    pub faces: Range<usize>,
+}
+
+/// All faces of an object that use one material.
+pub struct Submesh<'a> {
+    object: Object<'a>,
+    material: Option<usize>,
+}
+
+/// An iterator over all objects in a file.
+pub struct Objects<'a> {
+    wavefront: &'a Wavefront,
+    objects: ::std::slice::Iter<'a, ObjectData>,
+}
+
+/// Options that control how a file is parsed.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Skip malformed statements instead of failing.
+    ///
+    /// Every skipped statement is recorded as a `Warning`.
+    pub lenient: bool,
+}
+
+/// A problem found while parsing that did not stop the file from loading.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// The `.obj` or `.mtl` file that the problem is in.
+    pub file: String,
+    /// The one-based line number.
+    pub line: usize,
+    /// The one-based column number.
+    pub column: usize,
+    pub message: String,
+}
+
+/// The kind of problem a `Warning` describes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A malformed statement was skipped because the file was parsed leniently.
+    InvalidStatement,
+    /// A statement or texture option that is not supported was ignored.
+    Unsupported,
+    /// A material was used without being defined.
+    UnknownMaterial,
+    /// A texture file does not exist at its resolved path.
+    MissingTexture(PathBuf),
+}
+
+struct ObjectData {
+    name: String,
+    faces: Vec<Face>,
+    lines: Vec<Vec<Corner>>,
+    points: Vec<Corner>,
+}
+
+struct Face {
+    corners: Vec<Corner>,
+    material: Option<usize>,
+    /// An index into `Wavefront::group_sets`.
+    groups: usize,
+    smoothing_group: Option<u32>,
+}
+
+/// The triangles of an object that use a material.
+struct MaterialGroup {
+    material: Option<usize>,
+    indices: Vec<u64>,
+    /// The smoothing group of each triangle.
+    smoothing_groups: Vec<Option<u32>>,
+}
+
+/// The attribute indices of a single face corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    texture_coords: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// A texture map statement of a material, such as `map_Kd`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureMap {
+    /// The image file, as written in the material file.
+    pub path: String,
+    /// The scale of the texture coordinates (`-s`).
+    pub scale: Vector,
+    /// The offset of the texture coordinates (`-o`).
+    pub offset: Vector,
+    /// The multiplier applied to bump map values (`-bm`).
+    pub bump_multiplier: f32,
+    /// Whether texture coordinates are clamped to `0..1` (`-clamp`).
+    pub clamp: bool,
+    /// Whether the texture blends horizontally (`-blendu`).
+    pub blend_u: bool,
+    /// Whether the texture blends vertically (`-blendv`).
+    pub blend_v: bool,
+    /// The channel used by scalar textures (`-imfchan`).
+    pub channel: Option<Channel>,
+    resolved_path: Option<PathBuf>,
+}
+
+/// A channel of a texture image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Matte,
+    Luminance,
+    Depth,
+}
+
+struct MaterialData {
+    name: String,
+    ambient: Color,
+    diffuse: Color,
+    specular: Color,
+    emissive: Color,
+    shininess: f32,
+    dissolve: f32,
+    optical_density: f32,
+    illumination_model: Option<u32>,
+    roughness: Option<f32>,
+    metallic: Option<f32>,
+    sheen: Option<f32>,
+    clearcoat_thickness: Option<f32>,
+    clearcoat_roughness: Option<f32>,
+    anisotropy: Option<f32>,
+    anisotropy_rotation: Option<f32>,
+    ambient_texture: Option<TextureMap>,
+    diffuse_texture: Option<TextureMap>,
+    specular_texture: Option<TextureMap>,
+    emissive_texture: Option<TextureMap>,
+    normal_texture: Option<TextureMap>,
+    bump_texture: Option<TextureMap>,
+    dissolve_texture: Option<TextureMap>,
+    roughness_texture: Option<TextureMap>,
+    metallic_texture: Option<TextureMap>,
+    sheen_texture: Option<TextureMap>,
+    /// The material file on disk.
+    source: Option<PathBuf>,
+}
+
+/// Loads a Wavefront `.obj` file from disk.
+///
+/// Material files will be automatically loaded.
+pub fn from_path<S>(path: S) -> Result<Wavefront, Error>
+    where S: AsRef<Path> {
+    from_path_with_options(path, &Options::default())
+}
+
+/// Loads a Wavefront `.obj` file from disk with custom options.
+///
+/// Material files will be automatically loaded, relative to the directory
+/// of the `.obj` file.
+pub fn from_path_with_options<S>(path: S, options: &Options) -> Result<Wavefront, Error>
+    where S: AsRef<Path> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut wavefront = parse::parse(&mut reader, &path.display().to_string(), Some(directory), options, |mtl_path| {
+        Ok(BufReader::new(File::open(directory.join(mtl_path))?))
+    })?;
+
+    wavefront.path = Some(path.to_owned());
+    Ok(wavefront)
+}
+
+/// Loads a Wavefront `.obj` file from memory.
+///
+/// You must provide a closure that maps each material file path
+/// to its corresponding byte stream.
+pub fn from_memory<BO, BM>(reader: &mut BO,
+                           material_loader: impl Fn(&Path) -> BM)
+    -> Result<Wavefront, Error>
+    where BO: BufRead, BM: BufRead {
+    from_memory_with_options(reader, material_loader, &Options::default())
+}
+
+/// Loads a Wavefront `.obj` file from memory with custom options.
+pub fn from_memory_with_options<BO, BM>(reader: &mut BO,
+                                        material_loader: impl Fn(&Path) -> BM,
+                                        options: &Options)
+    -> Result<Wavefront, Error>
+    where BO: BufRead, BM: BufRead {
+    parse::parse(reader, "<memory>", None, options, |mtl_path| Ok(material_loader(mtl_path)))
+}
+
+impl Wavefront {
+    /// All of the objects contained within the wavefront.
+    pub fn objects(&self) -> Objects<'_> {
+        Objects { wavefront: self, objects: self.objects.iter() }
+    }
+
+    /// Gets the smoothing group of every triangle of the model built from
+    /// the whole file, in order.
+    pub fn smoothing_groups(&self) -> Vec<Option<u32>> {
+        self.objects().flat_map(|object| object.smoothing_groups()).collect()
+    }
+
+    /// Gets the problems that were found while loading the file.
+    ///
+    /// Unless the file was loaded with `Options::lenient`, these are only
+    /// problems that do not affect the loaded geometry, such as unsupported
+    /// statements or references to unknown materials.
+    pub fn warnings(&self) -> &[Warning] { &self.warnings }
+
+    /// Gets all materials loaded from the material files, in the order
+    /// they were defined.
+    pub fn materials(&self) -> impl Iterator<Item = Material<'_>> {
+        self.materials.iter().map(|material| Material { material })
+    }
+
+    /// Gets the `.obj` file the wavefront was loaded from.
+    ///
+    /// This is `None` for files loaded from memory.
+    pub fn path(&self) -> Option<&Path> { self.path.as_deref() }
+
+    /// Sets how polygons are split into triangles when building a model.
+    ///
+    /// Objects taken from the wavefront afterwards use the same strategy.
+    pub fn with_triangulation(mut self, triangulation: Triangulation) -> Self {
+        self.triangulation = triangulation;
+        self
+    }
+}
+
+impl<'a> Object<'a> {
+    /// Gets the name of the object.
+    pub fn name(&self) -> &str { &self.object.name }
+
+    /// Gets the material associated with the object.
+    ///
+    /// If the object uses several materials, this is the material
+    /// of its first face.
+    pub fn material(&self) -> Option<Material<'a>> {
+        self.wavefront.material(self.object.faces.first().and_then(|face| face.material))
+    }
+
+    /// Gets the names of all groups that faces of the object belong to,
+    /// in order of first use.
+    pub fn groups(&self) -> Vec<&'a str> {
+        let wavefront = self.wavefront;
+        let mut names: Vec<&'a str> = Vec::new();
+
+        for face in self.object.faces.iter() {
+            for name in wavefront.group_sets[face.groups].iter() {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Gets the runs of consecutive faces that share a material.
+    pub fn material_ranges(&self) -> Vec<MaterialRange<'a>> {
+        let mut ranges: Vec<(Option<usize>, Range<usize>)> = Vec::new();
+
+        for (i, face) in self.object.faces.iter().enumerate() {
+            match ranges.last_mut() {
+                Some(&mut (material, ref mut faces)) if material == face.material => faces.end = i + 1,
+                _ => ranges.push((face.material, i..i + 1)),
+            }
+        }
+
+        ranges.into_iter().map(|(material, faces)| MaterialRange { material: self.wavefront.material(material), faces }).collect()
+    }
+
+    /// Splits the object into one submesh per material, in order of first use.
+    pub fn submeshes(&self) -> Vec<Submesh<'a>> {
+        let mut materials: Vec<Option<usize>> = Vec::new();
+        for face in self.object.faces.iter() {
+            if !materials.contains(&face.material) {
+                materials.push(face.material);
+            }
+        }
+
+        materials.into_iter().map(|material| Submesh { object: *self, material }).collect()
+    }
+
+    /// Gets the smoothing group of every triangle of a model built from
+    /// the object, in order.
+    ///
+    /// This lines up with the triangles of `Model::new(object)`, for use with
+    /// `process::NormalOptions::smoothing_groups`.
+    pub fn smoothing_groups(&self) -> Vec<Option<u32>> {
+        let (_, groups) = self.build_mesh::<Vertex, _>(|_| true);
+        groups.into_iter().flat_map(|group| group.smoothing_groups).collect()
+    }
+
+    /// Gets the faces of the object, with their original number of corners.
+    pub fn polygons(&self) -> impl Iterator<Item = Polygon<'a>> + 'a {
+        let wavefront = self.wavefront;
+        self.object.faces.iter().map(move |face| Polygon { wavefront, face })
+    }
+
+    /// Gets the polylines (`l` statements) of the object.
+    ///
+    /// These are not part of models built from the object.
+    pub fn lines(&self) -> Vec<Vec<Vertex>> {
+        self.object.lines.iter().map(|line| {
+            line.iter().map(|corner| self.wavefront.vertex(corner)).collect()
+        }).collect()
+    }
+
+    /// Gets the points (`p` statements) of the object.
+    ///
+    /// These are not part of models built from the object.
+    pub fn points(&self) -> Vec<Vertex> {
+        self.object.points.iter().map(|corner| self.wavefront.vertex(corner)).collect()
+    }
+
+    /// Sets how polygons are split into triangles when building a model.
+    pub fn with_triangulation(mut self, triangulation: Triangulation) -> Self {
+        self.triangulation = triangulation;
+        self
+    }
+
+    /// Appends the faces of the object to a model, with one submesh per material.
+    fn append_to<V, I, F>(&self, model: &mut Model<V, I>, include: F) -> Result<(), Error>
+        where V: ::Vertex + From<Vertex>, I: Index, F: Fn(&Face) -> bool {
+        let (vertices, groups) = self.build_mesh::<V, _>(include);
+        let first_vertex = model.mesh.vertices.len() as u64;
+        model.mesh.vertices.extend(vertices);
+
+        for MaterialGroup { material, indices, .. } in groups {
+            let index_offset = model.mesh.indices.len();
+            for index in indices {
+                // Each object has indices relative to itself.
+                model.mesh.indices.push(I::from_u64(first_vertex + index)?);
+            }
+
+            let index_count = model.mesh.indices.len() - index_offset;
+            model.submeshes.push(::Submesh {
+                name: self.name().to_owned(),
+                index_offset,
+                index_count,
+                material,
+                bounds: model.mesh.bounds(index_offset..index_offset + index_count),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Builds the vertex list of the object, and an index list for each
+    /// material in order of first use.
+    ///
+    /// Each unique combination of position, texture coordinates and
+    /// normal becomes one vertex, in order of first use.
+    fn build_mesh<V, F>(&self, include: F) -> (Vec<V>, Vec<MaterialGroup>)
+        where V: From<Vertex>, F: Fn(&Face) -> bool {
+        let mut vertex_indices: HashMap<Corner, u64> = HashMap::new();
+        let mut vertices = Vec::new();
+        let mut groups: Vec<MaterialGroup> = Vec::new();
+
+        for face in self.object.faces.iter().filter(|face| include(face)) {
+            let face_indices: Vec<u64> = face.corners.iter().map(|corner| {
+                *vertex_indices.entry(*corner).or_insert_with(|| {
+                    vertices.push(V::from(self.wavefront.vertex(corner)));
+                    vertices.len() as u64 - 1
+                })
+            }).collect();
+
+            let group = match groups.iter().position(|group| group.material == face.material) {
+                Some(group) => group,
+                None => {
+                    groups.push(MaterialGroup { material: face.material, indices: Vec::new(), smoothing_groups: Vec::new() });
+                    groups.len() - 1
+                },
+            };
+
+            let positions: Vec<Vector> = face.corners.iter().map(|c| self.wavefront.positions[c.position]).collect();
+            for triangle in self.triangulation.triangulate(&positions).iter() {
+                groups[group].indices.extend(triangle.iter().map(|&corner| face_indices[corner]));
+                groups[group].smoothing_groups.push(face.smoothing_group);
+            }
+        }
+
+        (vertices, groups)
+    }
+}
+
+impl Wavefront {
+    fn vertex(&self, corner: &Corner) -> Vertex {
+        Vertex {
+            position: self.positions[corner.position],
+            normal: corner.normal.map(|i| self.normals[i]),
+            texture_coords: corner.texture_coords.map(|i| self.texture_coords[i]),
+            color: self.colors[corner.position],
+        }
+    }
+
+    fn material(&self, id: Option<usize>) -> Option<Material<'_>> {
+        id.map(|id| Material { material: &self.materials[id] })
+    }
+
+    /// Creates a model with no geometry to build parts of the file into.
+    ///
+    /// The material table holds every material of the file, so that material
+    /// ids are the same for all parts.
+    fn empty_model<V,I>(&self) -> Model<V,I>
+        where V: ::Vertex, I: Index {
+        Model {
+            mesh: TriangularMesh::empty(),
+            materials: self.materials().map(::Material::from).collect(),
+            submeshes: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Polygon<'a> {
+    /// Gets the number of corners of the face.
+    pub fn arity(&self) -> usize { self.face.corners.len() }
+
+    /// Gets the corners of the face, in winding order.
+    pub fn vertices(&self) -> Vec<Vertex> {
+        self.face.corners.iter().map(|corner| self.wavefront.vertex(corner)).collect()
+    }
+
+    /// Gets the material of the face.
+    pub fn material(&self) -> Option<Material<'a>> {
+        self.wavefront.material(self.face.material)
+    }
+
+    /// Gets the names of the groups the face belongs to.
+    pub fn groups(&self) -> &'a [String] { &self.wavefront.group_sets[self.face.groups] }
+
+    /// Gets the smoothing group of the face, or `None` if smoothing is off.
+    pub fn smoothing_group(&self) -> Option<u32> { self.face.smoothing_group }
+}
+
+impl<'a> Submesh<'a> {
+    /// Gets the material shared by the faces of the submesh.
+    pub fn material(&self) -> Option<Material<'a>> { self.object.wavefront.material(self.material) }
+}
+
+impl<'a> Material<'a> {
+    /// Gets the name of the material.
+    pub fn name(&self) -> &str { &self.material.name }
+
+    /// Gets the `.mtl` file the material was loaded from.
+    ///
+    /// This is `None` for files loaded from memory.
+    pub fn path(&self) -> Option<&'a Path> { self.material.source.as_deref() }
+
+    /// Gets the ambient color.
+    pub fn ambient_color(&self) -> Color { self.material.ambient }
+
+    /// Gets the diffuse color.
+    pub fn diffuse_color(&self) -> Color { self.material.diffuse }
+
+    /// Gets the specular color.
+    pub fn specular_color(&self) -> Color { self.material.specular }
+
+    /// Gets the shininess factor.
+    pub fn shininess(&self) -> f32 { self.material.shininess }
+
+    /// Gets the opacity factor.
+    pub fn alpha(&self) -> f32 { self.material.dissolve }
+
+    /// Gets the optical density.
+    pub fn optical_density(&self) -> f32 { self.material.optical_density }
+
+    /// Gets the emissive color.
+    pub fn emissive_color(&self) -> Color { self.material.emissive }
+
+    /// Gets the illumination model (`illum`).
+    pub fn illumination_model(&self) -> Option<u32> { self.material.illumination_model }
+
+    /// Gets the PBR roughness factor (`Pr`).
+    pub fn roughness(&self) -> Option<f32> { self.material.roughness }
+
+    /// Gets the PBR metallic factor (`Pm`).
+    pub fn metallic(&self) -> Option<f32> { self.material.metallic }
+
+    /// Gets the PBR sheen factor (`Ps`).
+    pub fn sheen(&self) -> Option<f32> { self.material.sheen }
+
+    /// Gets the clearcoat thickness (`Pc`).
+    pub fn clearcoat_thickness(&self) -> Option<f32> { self.material.clearcoat_thickness }
+
+    /// Gets the clearcoat roughness (`Pcr`).
+    pub fn clearcoat_roughness(&self) -> Option<f32> { self.material.clearcoat_roughness }
+
+    /// Gets the anisotropy (`aniso`).
+    pub fn anisotropy(&self) -> Option<f32> { self.material.anisotropy }
+
+    /// Gets the anisotropy rotation (`anisor`).
+    pub fn anisotropy_rotation(&self) -> Option<f32> { self.material.anisotropy_rotation }
+
+    /// Gets the ambient texture image file.
+    pub fn ambient_texture(&self) -> &str { texture_path(&self.material.ambient_texture) }
+
+    /// Gets the diffuse texture image file.
+    pub fn diffuse_texture(&self) -> &str { texture_path(&self.material.diffuse_texture) }
+
+    /// Gets the specular texture image file.
+    pub fn specular_texture(&self) -> &str { texture_path(&self.material.specular_texture) }
+
+    /// Gets the normal texture image file.
+    ///
+    /// Falls back to the bump map if the material has no `norm` map.
+    pub fn normal_texture(&self) -> &str {
+        texture_path(self.material.normal_texture.as_ref().or(self.material.bump_texture.as_ref()))
+    }
+
+    /// Gets the dissolve texture image file.
+    pub fn dissolve_texture(&self) -> &str { texture_path(&self.material.dissolve_texture) }
+
+    /// Gets the ambient texture map (`map_Ka`).
+    pub fn ambient_texture_map(&self) -> Option<&'a TextureMap> { self.material.ambient_texture.as_ref() }
+
+    /// Gets the diffuse texture map (`map_Kd`).
+    pub fn diffuse_texture_map(&self) -> Option<&'a TextureMap> { self.material.diffuse_texture.as_ref() }
+
+    /// Gets the specular texture map (`map_Ks`).
+    pub fn specular_texture_map(&self) -> Option<&'a TextureMap> { self.material.specular_texture.as_ref() }
+
+    /// Gets the emissive texture map (`map_Ke`).
+    pub fn emissive_texture_map(&self) -> Option<&'a TextureMap> { self.material.emissive_texture.as_ref() }
+
+    /// Gets the normal map (`norm`).
+    pub fn normal_texture_map(&self) -> Option<&'a TextureMap> { self.material.normal_texture.as_ref() }
+
+    /// Gets the bump map (`bump` or `map_Bump`).
+    pub fn bump_texture_map(&self) -> Option<&'a TextureMap> { self.material.bump_texture.as_ref() }
+
+    /// Gets the dissolve texture map (`map_d`).
+    pub fn dissolve_texture_map(&self) -> Option<&'a TextureMap> { self.material.dissolve_texture.as_ref() }
+
+    /// Gets the roughness texture map (`map_Pr`).
+    pub fn roughness_texture_map(&self) -> Option<&'a TextureMap> { self.material.roughness_texture.as_ref() }
+
+    /// Gets the metallic texture map (`map_Pm`).
+    pub fn metallic_texture_map(&self) -> Option<&'a TextureMap> { self.material.metallic_texture.as_ref() }
+
+    /// Gets the sheen texture map (`map_Ps`).
+    pub fn sheen_texture_map(&self) -> Option<&'a TextureMap> { self.material.sheen_texture.as_ref() }
+}
+
+impl TextureMap {
+    /// Creates a texture map with the default options.
+    pub fn new(path: String) -> Self {
+        TextureMap {
+            path,
+            scale: Vector(1.0, 1.0, 1.0),
+            offset: Vector(0.0, 0.0, 0.0),
+            bump_multiplier: 1.0,
+            clamp: false,
+            blend_u: true,
+            blend_v: true,
+            channel: None,
+            resolved_path: None,
+        }
+    }
+
+    /// Gets the location of the image file on disk.
+    ///
+    /// Relative paths are resolved against the directory of the material
+    /// file, and backslashes are treated as directory separators. This is
+    /// `None` if the location of the material file is not known, such as
+    /// for files loaded from memory. The file may not exist, in which case
+    /// loading the material gave a `WarningKind::MissingTexture` warning.
+    pub fn resolved_path(&self) -> Option<&Path> { self.resolved_path.as_deref() }
+}
+
+fn texture_path<'a, T: Into<Option<&'a TextureMap>>>(texture: T) -> &'a str {
+    texture.into().map(|texture| texture.path.as_str()).unwrap_or("")
+}
+
+impl BuildModel for Wavefront
+{
+    type Vertex = Vertex;
+
+    /// Builds every object into a single model, with a submesh for each
+    /// material of each object.
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut model = self.empty_model();
+
+        for object in self.objects() {
+            object.append_to(&mut model, |_| true)?;
+        }
+
+        Ok(model)
+    }
+}
+
+/// Builds a flat scene, with a single node drawing the whole file.
+impl BuildScene for Wavefront {
+    type Vertex = Vertex;
+
+    fn build_scene<V,I>(self) -> Result<Scene<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        Ok(Scene::from_model(self.build_model()?))
+    }
+}
+
+impl<'a> BuildModel for Object<'a> {
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut model = self.wavefront.empty_model();
+        self.append_to(&mut model, |_| true)?;
+        Ok(model)
+    }
+}
+
+impl<'a> BuildModel for Submesh<'a> {
+    type Vertex = Vertex;
+
+    fn build_model<V,I>(self) -> Result<Model<V,I>, Error>
+        where V: ::Vertex, I: Index, V: From<Vertex> {
+        let mut model = self.object.wavefront.empty_model();
+        self.object.append_to(&mut model, |face| face.material == self.material)?;
+        Ok(model)
+    }
+}
+
+impl<'a> Iterator for Objects<'a> {
+    type Item = Object<'a>;
+
+    fn next(&mut self) -> Option<Object<'a>> {
+        let wavefront = self.wavefront;
+        self.objects.next().map(|o| Object { wavefront, object: o, triangulation: wavefront.triangulation })
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+    }
+}
+
+/// Converts the classic Phong parameters of a material to the closest
+/// metallic-roughness equivalent, unless the PBR extension is used.
+impl<'a> From<Material<'a>> for ::Material {
+    fn from(material: Material<'a>) -> ::Material {
+        let texture = |texture: Option<&TextureMap>| texture.map(|texture| {
+            ::Texture::from_path(texture.resolved_path().map(Path::to_owned).unwrap_or_else(|| PathBuf::from(&texture.path)))
+        });
+        let translucent = material.alpha() < 1.0 || material.dissolve_texture_map().is_some();
+
+        ::Material {
+            name: material.name().to_owned(),
+            base_color: material.diffuse_color(),
+            opacity: material.alpha(),
+            emissive: material.emissive_color(),
+            metallic: material.metallic().unwrap_or(0.0),
+            // The usual mapping between a Phong exponent and a Beckmann roughness.
+            roughness: material.roughness().unwrap_or_else(|| (2.0 / (material.shininess() + 2.0)).sqrt()),
+            alpha_mode: if translucent { ::AlphaMode::Blend } else { ::AlphaMode::Opaque },
+            double_sided: false,
+            base_color_texture: texture(material.diffuse_texture_map()),
+            opacity_texture: texture(material.dissolve_texture_map()),
+            emissive_texture: texture(material.emissive_texture_map()),
+            metallic_texture: texture(material.metallic_texture_map()),
+            roughness_texture: texture(material.roughness_texture_map()),
+            normal_texture: texture(material.normal_texture_map().or_else(|| material.bump_texture_map())),
+            occlusion_texture: None,
+        }
+    }
+}
+
+impl From<Vertex> for Vector {
+    fn from(v: Vertex) -> Vector {
+        v.position
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Model, Vector, ErrorKind};
+    use std::path::Path;
+
+    fn cube() -> Wavefront {
+        from_path(Path::new("res/cube.obj")).unwrap()
+    }
+
+    fn parse(text: &str, options: &Options) -> Result<Wavefront, Error> {
+        from_memory_with_options(&mut text.as_bytes(), |_| &b""[..], options)
+    }
+
+    pub type Vertex = Vector;
+
+    #[test]
+    fn can_build_file() {
+        let cube: Model<Vertex, u64> = Model::new(cube()).unwrap();
+
+        assert_eq!(cube.mesh.vertices.len(), 24);
+        assert_eq!(cube.mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn can_enumerate_objects() {
+        let cube = cube();
+        assert_eq!(cube.objects().count(), 1);
+        assert_eq!(cube.objects().next().unwrap().name(), "Cube");
+    }
+
+    #[test]
+    fn can_build_object() {
+        let cube: Model<Vertex, u64> = Model::new(cube().objects().next().unwrap()).unwrap();
+        assert_eq!(cube.mesh.vertices.len(), 24);
+        assert_eq!(cube.mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn loads_materials() {
+        let world = from_path("res/world.obj").unwrap();
+        let names: Vec<_> = world.objects().map(|o| (o.name().to_owned(), o.material().unwrap().name().to_owned())).collect();
+
+        assert_eq!(names, vec![
+            ("first_door_cube1".to_owned(), "None".to_owned()),
+            ("second_door_cube2".to_owned(), "None".to_owned()),
+            ("ground_cube3".to_owned(), "Material".to_owned()),
+        ]);
+
+        let ground = world.objects().last().unwrap().material().unwrap();
+        assert_eq!(ground.diffuse_color(), Color(0.64, 0.64, 0.64));
+        assert_eq!(ground.shininess(), 96.07843);
+        assert!(world.warnings().is_empty());
+    }
+
+    #[test]
+    fn supports_negative_indices() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n";
+        let model: Model<Vertex, u8> = Model::new(parse(text, &Options::default()).unwrap()).unwrap();
+        assert_eq!(model.mesh.vertices, vec![Vector(0.0, 0.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0)]);
+    }
+
+    #[test]
+    fn reports_location_of_errors() {
+        let text = "o broken\nv 0 0 0\nv 1 0 zero\n";
+        let error = parse(text, &Options::default()).err().unwrap();
+
+        match *error.kind() {
+            ErrorKind::WavefrontParseError(ref file, line, column, _) => {
+                assert_eq!((file.as_str(), line, column), ("<memory>", 3, 7));
+            },
+            ref kind => panic!("unexpected error: {}", kind),
+        }
+        assert_eq!(error.to_string(), "<memory>:3:7: invalid number 'zero'");
+    }
+
+    #[test]
+    fn reports_out_of_range_indices() {
+        let error = parse("v 0 0 0\nf 1 2 3\n", &Options::default()).err().unwrap();
+        assert_eq!(error.to_string(), "<memory>:2:5: vertex index 2 is out of range");
+    }
+
+    #[test]
+    fn lenient_mode_skips_bad_statements() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 bad 1\nf 1 2 3\nf 1 2 9\n";
+        let wavefront = parse(text, &Options { lenient: true }).unwrap();
+
+        let messages: Vec<_> = wavefront.warnings().iter().map(ToString::to_string).collect();
+        assert_eq!(messages, vec![
+            "<memory>:4:5: invalid number 'bad'".to_owned(),
+            "<memory>:6:7: vertex index 9 is out of range".to_owned(),
+        ]);
+
+        let model: Model<Vertex, u8> = Model::new(wavefront).unwrap();
+        assert_eq!(model.mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn reports_errors_in_material_files() {
+        let result = from_memory(&mut &b"mtllib broken.mtl\n"[..], |_| &b"newmtl a\nKd 1 1\n"[..]);
+        assert_eq!(result.err().unwrap().to_string(), "broken.mtl:2:1: expected 3 numbers but found 2");
+    }
+
+    #[test]
+    fn preserves_face_arities() {
+        let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\nf 1 2 3 4\nf 2 5 3\n";
+        let wavefront = parse(text, &Options::default()).unwrap();
+        let object = wavefront.objects().next().unwrap();
+
+        let arities: Vec<_> = object.polygons().map(|p| p.arity()).collect();
+        assert_eq!(arities, vec![4, 3]);
+        assert_eq!(object.polygons().next().unwrap().vertices()[2].position, Vector(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn loads_lines_and_points() {
+        let text = "o rail\nv 0 0 0\nv 1 0 0\nv 2 1 0\nvt 0.5 0\nl 1/1 2/1 3/1\np 1 3\n";
+        let wavefront = parse(text, &Options::default()).unwrap();
+        let object = wavefront.objects().next().unwrap();
+
+        let lines = object.lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].iter().map(|v| v.position).collect::<Vec<_>>(),
+                   vec![Vector(0.0, 0.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(2.0, 1.0, 0.0)]);
+        assert_eq!(lines[0][0].texture_coords, Some(TexCoord { u: 0.5, v: 0.0, w: None }));
+        assert_eq!(object.points().len(), 2);
+        assert!(wavefront.warnings().is_empty());
+
+        let model: Model<Vector, u8> = Model::new(object).unwrap();
+        assert!(model.mesh.indices.is_empty());
+    }
+
+    #[test]
+    fn triangulates_concave_polygons_with_ear_clipping() {
+        // An arrowhead, which is concave at its second corner.
+        let text = "v 0 0 0\nv 2 1 0\nv 4 0 0\nv 2 3 0\nf 1 2 3 4\n";
+
+        let fan: Model<Vector, u8> = Model::new(parse(text, &Options::default()).unwrap()).unwrap();
+        assert_eq!(fan.mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+
+        let wavefront = parse(text, &Options::default()).unwrap().with_triangulation(Triangulation::EarClipping);
+        let clipped: Model<Vector, u8> = Model::new(wavefront).unwrap();
+        assert_eq!(clipped.mesh.indices.len(), 6);
+
+        // Every triangle keeps the counter-clockwise winding of the polygon.
+        for triangle in clipped.mesh.triangles() {
+            let [a, b, c] = triangle.vertices;
+            assert!((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0) > 0.0);
+        }
+    }
+
+    const MULTI_MATERIAL: &str = "mtllib paint.mtl\n\
+        o Sign\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+        g post\ns 1\nusemtl red\nf 1 2 3\nf 1 3 4\n\
+        g face trim\ns off\nusemtl blue\nf 1 2 4\n\
+        usemtl red\nf 2 3 4\n";
+
+    fn multi_material() -> Wavefront {
+        from_memory(&mut MULTI_MATERIAL.as_bytes(), |_| &b"newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n"[..]).unwrap()
+    }
+
+    #[test]
+    fn exposes_groups_and_smoothing_groups() {
+        let wavefront = multi_material();
+        let sign = wavefront.objects().next().unwrap();
+
+        assert_eq!(sign.groups(), vec!["post", "face", "trim"]);
+        let polygons: Vec<_> = sign.polygons().map(|p| (p.groups().to_vec(), p.smoothing_group())).collect();
+        assert_eq!(polygons[0], (vec!["post".to_owned()], Some(1)));
+        assert_eq!(polygons[2], (vec!["face".to_owned(), "trim".to_owned()], None));
+    }
+
+    #[test]
+    fn exposes_material_ranges() {
+        let wavefront = multi_material();
+        let ranges: Vec<_> = wavefront.objects().next().unwrap().material_ranges().into_iter()
+            .map(|r| (r.material.unwrap().name().to_owned(), r.faces)).collect();
+
+        assert_eq!(ranges, vec![("red".to_owned(), 0..2), ("blue".to_owned(), 2..3), ("red".to_owned(), 3..4)]);
+    }
+
+    #[test]
+    fn builds_one_submesh_per_material() {
+        let wavefront = multi_material();
+        let submeshes = wavefront.objects().next().unwrap().submeshes();
+
+        let names: Vec<_> = submeshes.iter().map(|s| s.material().unwrap().name().to_owned()).collect();
+        assert_eq!(names, vec!["red", "blue"]);
+
+        let models: Vec<Model<Vector, u8>> = submeshes.into_iter().map(|s| Model::new(s).unwrap()).collect();
+        assert_eq!(models[0].mesh.indices, vec![0, 1, 2, 0, 2, 3, 1, 2, 3]);
+        assert_eq!(models[1].mesh.indices, vec![0, 1, 2]);
+        assert_eq!(models[1].mesh.vertices, vec![Vector(0.0, 0.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0)]);
+    }
+
+    fn pbr_material(mtl: &'static str) -> Wavefront {
+        let text = "mtllib pbr.mtl\nusemtl steel\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        from_memory(&mut text.as_bytes(), |_| mtl.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn loads_pbr_parameters() {
+        let wavefront = pbr_material("newmtl steel\nKd 0.5 0.5 0.5\nKe 1 0.5 0\nillum 2\n\
+            Pr 0.25\nPm 1\nPs 0.1\nPc 0.2\nPcr 0.3\naniso 0.4\nanisor 0.5\n\
+            map_Pr steel_roughness.png\nmap_Pm steel_metallic.png\nmap_Ke steel_glow.png\nnorm steel_normal.png\n");
+        let steel = wavefront.objects().next().unwrap().material().unwrap();
+
+        assert_eq!(steel.emissive_color(), Color(1.0, 0.5, 0.0));
+        assert_eq!(steel.illumination_model(), Some(2));
+        assert_eq!((steel.roughness(), steel.metallic(), steel.sheen()), (Some(0.25), Some(1.0), Some(0.1)));
+        assert_eq!((steel.clearcoat_thickness(), steel.clearcoat_roughness()), (Some(0.2), Some(0.3)));
+        assert_eq!((steel.anisotropy(), steel.anisotropy_rotation()), (Some(0.4), Some(0.5)));
+        assert_eq!(steel.roughness_texture_map().unwrap().path, "steel_roughness.png");
+        assert_eq!(steel.metallic_texture_map().unwrap().path, "steel_metallic.png");
+        assert_eq!(steel.emissive_texture_map().unwrap().path, "steel_glow.png");
+        assert_eq!(steel.normal_texture(), "steel_normal.png");
+        assert!(steel.sheen_texture_map().is_none());
+        assert!(wavefront.warnings().is_empty());
+    }
+
+    #[test]
+    fn parses_texture_options() {
+        let wavefront = pbr_material("newmtl steel\n\
+            map_Kd -s 2 2 -o 0.5 -clamp on -blendu off steel plate.png\n\
+            bump -bm 0.3 -imfchan l -t 1 1 1 steel_bump.png\n");
+        let steel = wavefront.objects().next().unwrap().material().unwrap();
+
+        let diffuse = steel.diffuse_texture_map().unwrap();
+        assert_eq!(diffuse.path, "steel plate.png");
+        assert_eq!(diffuse.scale, Vector(2.0, 2.0, 1.0));
+        assert_eq!(diffuse.offset, Vector(0.5, 0.0, 0.0));
+        assert!(diffuse.clamp && !diffuse.blend_u && diffuse.blend_v);
+        assert_eq!(steel.diffuse_texture(), "steel plate.png");
+
+        let bump = steel.bump_texture_map().unwrap();
+        assert_eq!((bump.path.as_str(), bump.bump_multiplier, bump.channel), ("steel_bump.png", 0.3, Some(Channel::Luminance)));
+        assert_eq!(steel.normal_texture(), "steel_bump.png");
+
+        let messages: Vec<_> = wavefront.warnings().iter().map(ToString::to_string).collect();
+        assert_eq!(messages, vec!["pbr.mtl:3:25: unsupported texture option '-t'".to_owned()]);
+    }
+
+    #[test]
+    fn resolves_texture_paths() {
+        let wavefront = from_path("res/crate.obj").unwrap();
+        assert_eq!(wavefront.path(), Some(Path::new("res/crate.obj")));
+
+        let wood = wavefront.objects().next().unwrap().material().unwrap();
+        assert_eq!(wood.path(), Some(Path::new("res/crate.mtl")));
+        assert_eq!(wood.diffuse_texture(), "textures\\wood.png");
+
+        let diffuse = wood.diffuse_texture_map().unwrap().resolved_path().unwrap();
+        assert_eq!(diffuse, Path::new("res/textures/wood.png"));
+        assert!(diffuse.is_file());
+    }
+
+    #[test]
+    fn reports_missing_textures() {
+        let wavefront = from_path("res/crate.obj").unwrap();
+        let missing = Path::new("res/textures/wood_normal.png").to_owned();
+
+        assert_eq!(wavefront.warnings(), &[Warning {
+            kind: WarningKind::MissingTexture(missing),
+            file: "crate.mtl".to_owned(),
+            line: 9,
+            column: 6,
+            message: "texture 'res/textures/wood_normal.png' not found".to_owned(),
+        }]);
+    }
+
+    #[test]
+    fn does_not_resolve_textures_in_memory() {
+        let wavefront = pbr_material("newmtl steel\nmap_Kd steel.png\n");
+        let steel = wavefront.objects().next().unwrap().material().unwrap();
+
+        assert_eq!(wavefront.path(), None);
+        assert_eq!(steel.path(), None);
+        assert_eq!(steel.diffuse_texture_map().unwrap().resolved_path(), None);
+        assert!(wavefront.warnings().is_empty());
+    }
+
+    #[test]
+    fn builds_material_table() {
+        let model: Model<Vector, u16> = Model::new(from_path("res/crate.obj").unwrap()).unwrap();
+
+        assert_eq!(model.materials.len(), 1);
+        let wood = &model.materials[0];
+        assert_eq!(wood.name, "Wood");
+        assert_eq!(wood.base_color, Color(0.8, 0.6, 0.4));
+        assert_eq!((wood.opacity, wood.metallic, wood.alpha_mode), (1.0, 0.0, ::AlphaMode::Opaque));
+        assert_eq!(wood.roughness, (2.0f32 / 12.0).sqrt());
+        assert_eq!(wood.base_color_texture, Some(::Texture::from_path("res/textures/wood.png")));
+        assert_eq!(wood.normal_texture, Some(::Texture::from_path("res/textures/wood_normal.png")));
+    }
+
+    #[test]
+    fn records_submesh_per_object_and_material() {
+        let world: Model<Vector, u32> = Model::new(from_path("res/world.obj").unwrap()).unwrap();
+        let names: Vec<_> = world.submeshes.iter().map(|s| (s.name.as_str(), s.material)).collect();
+        assert_eq!(names, vec![("first_door_cube1", Some(1)), ("second_door_cube2", Some(1)), ("ground_cube3", Some(0))]);
+        assert_eq!(world.materials[1].name, "None");
+
+        let total: usize = world.submeshes.iter().map(|s| s.index_count).sum();
+        assert_eq!(total, world.mesh.indices.len());
+
+        let sign: Model<Vector, u8> = Model::new(multi_material()).unwrap();
+        let ranges: Vec<_> = sign.submeshes.iter().map(|s| (s.index_offset, s.index_count, s.material)).collect();
+        assert_eq!(ranges, vec![(0, 9, Some(0)), (9, 3, Some(1))]);
+        assert_eq!(sign.submeshes[1].bounds, ::Bounds { min: Vector(0.0, 0.0, 0.0), max: Vector(1.0, 1.0, 0.0) });
+    }
+
+    #[test]
+    fn extracts_normals_and_texture_coords() {
+        let wavefront = from_path("res/crate.obj").unwrap();
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+        for polygon in wavefront.objects().next().unwrap().polygons() {
+            let vertices = polygon.vertices();
+            let uvs: Vec<_> = vertices.iter().map(|v| v.texture_coords.map(|t| (t.u, t.v, t.w))).collect();
+            assert_eq!(uvs, corners.iter().map(|&(u, v)| Some((u, v, None))).collect::<Vec<_>>());
+
+            let normal = vertices[0].normal.unwrap();
+            assert!(vertices.iter().all(|v| v.normal == Some(normal) && v.color.is_none()));
+        }
+
+        // Every corner keeps its own normal and UV, so the faces do not share vertices.
+        let model: Model<::load::Vertex, u16> = Model::new(wavefront).unwrap();
+        assert_eq!(model.mesh.vertices.len(), 24);
+        for triangle in model.mesh.triangles() {
+            let [a, b, c] = triangle.vertices;
+            let winding = (b.position - a.position).cross(c.position - a.position).normalize();
+            assert_eq!(Some(winding), a.normal);
+            assert!(a.texture_coords.is_some());
+        }
+    }
+
+    #[test]
+    fn reads_vertex_colors_and_3d_texture_coords() {
+        let text = "v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0.5 0.5 1\nvt 0.25 0.5 0.75\nvt 1\nf 1/1 2/2 3/1\n";
+        let wavefront = parse(text, &Options::default()).unwrap();
+        let vertices = wavefront.objects().next().unwrap().polygons().next().unwrap().vertices();
+
+        assert_eq!(vertices[1].color, Some(Color(0.0, 1.0, 0.0)));
+        assert_eq!(vertices[0].texture_coords, Some(TexCoord { u: 0.25, v: 0.5, w: Some(0.75) }));
+        assert_eq!(vertices[1].texture_coords, Some(TexCoord { u: 1.0, v: 0.0, w: None }));
+
+        let model: Model<::load::Vertex, u8> = Model::new(wavefront).unwrap();
+        assert_eq!(model.mesh.vertices[2].color, Some(Color(0.5, 0.5, 1.0)));
+        assert_eq!(model.mesh.vertices[2].texture_coords, Some([0.25, 0.5]));
+
+        let error = parse("v 0 0 0 1 1\n", &Options::default()).err().unwrap();
+        assert_eq!(error.to_string(), "<memory>:1:1: expected a position with an optional w or an RGB color but found 5 numbers");
+    }
+}
@@ -0,0 +1,575 @@
+//! The `.obj` and `.mtl` parsers.
+
+use super::{Wavefront, ObjectData, Face, Corner, MaterialData, Options, Warning, Triangulation};
+use super::{TextureMap, Channel, WarningKind, TexCoord};
+use {Vector, Color, Error, ErrorKind};
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+/// The name given to faces that appear before any object statement.
+const UNNAMED_OBJECT: &str = "unnamed_object";
+
+/// A problem at a specific place in the current file.
+struct Diagnostic {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+/// Why a statement could not be handled.
+enum Problem {
+    /// The statement is invalid, which is a warning in lenient mode.
+    Invalid(Diagnostic),
+    /// Parsing cannot continue, even in lenient mode.
+    Fatal(Error),
+}
+
+impl From<Diagnostic> for Problem {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Problem::Invalid(diagnostic)
+    }
+}
+
+/// A whitespace-separated word and the column it starts at.
+#[derive(Copy, Clone)]
+struct Word<'a> {
+    text: &'a str,
+    column: usize,
+}
+
+/// Reports problems in a single file, either failing or collecting warnings.
+struct Reporter<'a> {
+    file: String,
+    options: &'a Options,
+    warnings: Vec<Warning>,
+}
+
+/// Parses an `.obj` file.
+///
+/// If the directory of the file is known, the paths of material files and
+/// textures are resolved relative to it.
+pub fn parse<B, M, L>(reader: &mut B,
+                      file: &str,
+                      directory: Option<&Path>,
+                      options: &Options,
+                      material_loader: L) -> Result<Wavefront, Error>
+    where B: BufRead, M: BufRead, L: Fn(&Path) -> Result<M, Error> {
+    let mut reporter = Reporter { file: file.to_owned(), options, warnings: Vec::new() };
+    let mut wavefront = Wavefront {
+        positions: Vec::new(),
+        colors: Vec::new(),
+        texture_coords: Vec::new(),
+        normals: Vec::new(),
+        objects: Vec::new(),
+        materials: Vec::new(),
+        path: None,
+        group_sets: vec![Vec::new()],
+        warnings: Vec::new(),
+        triangulation: Triangulation::default(),
+    };
+
+    let mut material_ids: HashMap<String, usize> = HashMap::new();
+    let mut current_material = None;
+    let mut current_groups = 0;
+    let mut current_smoothing_group = None;
+    // Files without any `o` statements use groups to separate objects.
+    let mut seen_object_statement = false;
+
+    for_each_statement(reader, &mut reporter, |reporter, line, words| {
+        let keyword = words[0];
+        let arguments = &words[1..];
+        let at = |word: &Word, message: String| Diagnostic { line, column: word.column, message };
+
+        match keyword.text {
+            "v" => {
+                // Either `x y z [w]`, or `x y z r g b` with a vertex color.
+                let values = parse_floats(line, keyword, arguments, 3, 6)?;
+                if values.len() == 5 {
+                    return Err(at(&keyword, "expected a position with an optional w or an RGB color but found 5 numbers".to_owned()).into());
+                }
+
+                wavefront.positions.push(Vector(values[0], values[1], values[2]));
+                wavefront.colors.push(if values.len() == 6 { Some(Color(values[3], values[4], values[5])) } else { None });
+            },
+            "vt" => {
+                let uvw = parse_floats(line, keyword, arguments, 1, 3)?;
+                wavefront.texture_coords.push(TexCoord { u: uvw[0], v: uvw.get(1).cloned().unwrap_or(0.0), w: uvw.get(2).cloned() });
+            },
+            "vn" => {
+                let normal = parse_vector(line, keyword, arguments, 3, 3)?;
+                wavefront.normals.push(normal);
+            },
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(at(&keyword, format!("a face needs at least 3 vertices but found {}", arguments.len())).into());
+                }
+
+                let corners = parse_corners(line, arguments, &wavefront)?;
+                let face = Face {
+                    corners,
+                    material: current_material,
+                    groups: current_groups,
+                    smoothing_group: current_smoothing_group,
+                };
+                current_object(&mut wavefront.objects).faces.push(face);
+            },
+            "l" => {
+                if arguments.len() < 2 {
+                    return Err(at(&keyword, format!("a line needs at least 2 vertices but found {}", arguments.len())).into());
+                }
+
+                let corners = parse_corners(line, arguments, &wavefront)?;
+                current_object(&mut wavefront.objects).lines.push(corners);
+            },
+            "p" => {
+                let corners = parse_corners(line, arguments, &wavefront)?;
+                current_object(&mut wavefront.objects).points.extend(corners);
+            },
+            "o" => {
+                seen_object_statement = true;
+                current_groups = 0;
+                wavefront.objects.push(ObjectData::new(rest_of_line(arguments)));
+            },
+            "g" => {
+                if !seen_object_statement {
+                    wavefront.objects.push(ObjectData::new(rest_of_line(arguments)));
+                }
+
+                wavefront.group_sets.push(arguments.iter().map(|word| word.text.to_owned()).collect());
+                current_groups = wavefront.group_sets.len() - 1;
+            },
+            "s" => {
+                current_smoothing_group = match arguments {
+                    [id] if id.text == "off" => None,
+                    [id] => match id.text.parse() {
+                        Ok(0) => None,
+                        Ok(id) => Some(id),
+                        Err(_) => return Err(at(id, format!("invalid smoothing group '{}'", id.text)).into()),
+                    },
+                    _ => return Err(at(&keyword, format!("expected 1 smoothing group but found {}", arguments.len())).into()),
+                };
+            },
+            "usemtl" => {
+                let name = rest_of_line(arguments);
+                current_material = material_ids.get(&name).cloned();
+
+                if current_material.is_none() {
+                    reporter.warn(WarningKind::UnknownMaterial, at(&keyword, format!("unknown material '{}'", name)));
+                }
+            },
+            "mtllib" => {
+                for library in arguments {
+                    let library_path = normalize_path(library.text);
+                    let mut material_reader = material_loader(&library_path).map_err(|error| {
+                        at(library, format!("could not load material library: {}", error))
+                    })?;
+
+                    let source = directory.map(|directory| directory.join(&library_path));
+                    let (materials, warnings) = parse_mtl(&mut material_reader, library.text, source, reporter.options)
+                        .map_err(Problem::Fatal)?;
+                    for material in materials {
+                        material_ids.insert(material.name.clone(), wavefront.materials.len());
+                        wavefront.materials.push(material);
+                    }
+                    reporter.warnings.extend(warnings);
+                }
+            },
+            other => {
+                reporter.warn(WarningKind::Unsupported, at(&keyword, format!("unsupported statement '{}'", other)));
+            },
+        }
+
+        Ok(())
+    })?;
+
+    wavefront.objects.retain(|object| {
+        !object.faces.is_empty() || !object.lines.is_empty() || !object.points.is_empty()
+    });
+    wavefront.warnings = reporter.warnings;
+    Ok(wavefront)
+}
+
+/// Parses an `.mtl` file.
+///
+/// The `source` is the location of the file on disk, if known.
+fn parse_mtl<B>(reader: &mut B, file: &str, source: Option<PathBuf>, options: &Options)
+    -> Result<(Vec<MaterialData>, Vec<Warning>), Error>
+    where B: BufRead {
+    let directory = source.as_ref().map(|source| source.parent().unwrap_or(Path::new("")).to_owned());
+    let mut reporter = Reporter { file: file.to_owned(), options, warnings: Vec::new() };
+    let mut materials: Vec<MaterialData> = Vec::new();
+
+    for_each_statement(reader, &mut reporter, |reporter, line, words| {
+        let keyword = words[0];
+        let arguments = &words[1..];
+
+        if keyword.text == "newmtl" {
+            let mut material = MaterialData::new(rest_of_line(arguments));
+            material.source = source.clone();
+            materials.push(material);
+            return Ok(());
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(Diagnostic { line, column: keyword.column, message: "expected 'newmtl'".to_owned() }.into()),
+        };
+
+        let float = || parse_floats(line, keyword, arguments, 1, 1).map(|values| values[0]);
+        let mut texture = || parse_texture(reporter, line, keyword, arguments, directory.as_deref()).map(Some);
+
+        match keyword.text {
+            "Ka" => material.ambient = parse_color(line, keyword, arguments)?,
+            "Kd" => material.diffuse = parse_color(line, keyword, arguments)?,
+            "Ks" => material.specular = parse_color(line, keyword, arguments)?,
+            "Ke" => material.emissive = parse_color(line, keyword, arguments)?,
+            "Ns" => material.shininess = float()?,
+            "Ni" => material.optical_density = float()?,
+            "d" => material.dissolve = float()?,
+            "Tr" => material.dissolve = 1.0 - float()?,
+            "illum" => {
+                let word = arguments.first().ok_or_else(|| Diagnostic {
+                    line,
+                    column: keyword.column,
+                    message: "expected an illumination model".to_owned(),
+                })?;
+                let model = word.text.parse().map_err(|_| Diagnostic {
+                    line,
+                    column: word.column,
+                    message: format!("invalid illumination model '{}'", word.text),
+                })?;
+                material.illumination_model = Some(model);
+            },
+            "Pr" => material.roughness = Some(float()?),
+            "Pm" => material.metallic = Some(float()?),
+            "Ps" => material.sheen = Some(float()?),
+            "Pc" => material.clearcoat_thickness = Some(float()?),
+            "Pcr" => material.clearcoat_roughness = Some(float()?),
+            "aniso" => material.anisotropy = Some(float()?),
+            "anisor" => material.anisotropy_rotation = Some(float()?),
+            "map_Ka" => material.ambient_texture = texture()?,
+            "map_Kd" => material.diffuse_texture = texture()?,
+            "map_Ks" => material.specular_texture = texture()?,
+            "map_Ke" => material.emissive_texture = texture()?,
+            "norm" => material.normal_texture = texture()?,
+            "map_Bump" | "map_bump" | "bump" => material.bump_texture = texture()?,
+            "map_d" => material.dissolve_texture = texture()?,
+            "map_Pr" => material.roughness_texture = texture()?,
+            "map_Pm" => material.metallic_texture = texture()?,
+            "map_Ps" => material.sheen_texture = texture()?,
+            // Recognised, but not represented by `Material`.
+            "Tf" | "sharpness" => (),
+            other => {
+                let message = format!("unsupported statement '{}'", other);
+                reporter.warn(WarningKind::Unsupported, Diagnostic { line, column: keyword.column, message });
+            },
+        }
+
+        Ok(())
+    })?;
+
+    Ok((materials, reporter.warnings))
+}
+
+/// Splits a file into statements and feeds them to a handler.
+///
+/// Comments and blank lines are skipped, and lines ending in a backslash
+/// are joined with the line after them.
+fn for_each_statement<B, F>(reader: &mut B, reporter: &mut Reporter, mut handler: F) -> Result<(), Error>
+    where B: BufRead,
+          F: FnMut(&mut Reporter, usize, &[Word]) -> Result<(), Problem> {
+    let mut lines = reader.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let mut line = line?;
+
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, continuation)) => line.push_str(&continuation?),
+                None => break,
+            }
+        }
+
+        let content = match line.find('#') {
+            Some(comment_start) => &line[..comment_start],
+            None => &line[..],
+        };
+
+        let words = split_words(content);
+        if words.is_empty() { continue }
+
+        match handler(reporter, line_number, &words) {
+            Ok(()) => (),
+            Err(Problem::Invalid(diagnostic)) => {
+                if reporter.options.lenient {
+                    reporter.warn(WarningKind::InvalidStatement, diagnostic);
+                } else {
+                    return Err(reporter.error(diagnostic));
+                }
+            },
+            Err(Problem::Fatal(error)) => return Err(error),
+        }
+    }
+
+    Ok(())
+}
+
+impl<'a> Reporter<'a> {
+    fn warn(&mut self, kind: WarningKind, diagnostic: Diagnostic) {
+        self.warnings.push(Warning {
+            kind,
+            file: self.file.clone(),
+            line: diagnostic.line,
+            column: diagnostic.column,
+            message: diagnostic.message,
+        });
+    }
+
+    fn error(&self, diagnostic: Diagnostic) -> Error {
+        ErrorKind::WavefrontParseError(self.file.clone(), diagnostic.line, diagnostic.column, diagnostic.message).into()
+    }
+}
+
+impl ObjectData {
+    fn new(name: String) -> Self {
+        ObjectData { name, faces: Vec::new(), lines: Vec::new(), points: Vec::new() }
+    }
+}
+
+impl MaterialData {
+    fn new(name: String) -> Self {
+        MaterialData {
+            name,
+            ambient: Color(0.0, 0.0, 0.0),
+            diffuse: Color(0.0, 0.0, 0.0),
+            specular: Color(0.0, 0.0, 0.0),
+            emissive: Color(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            dissolve: 1.0,
+            optical_density: 1.0,
+            illumination_model: None,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat_thickness: None,
+            clearcoat_roughness: None,
+            anisotropy: None,
+            anisotropy_rotation: None,
+            ambient_texture: None,
+            diffuse_texture: None,
+            specular_texture: None,
+            emissive_texture: None,
+            normal_texture: None,
+            bump_texture: None,
+            dissolve_texture: None,
+            roughness_texture: None,
+            metallic_texture: None,
+            sheen_texture: None,
+            source: None,
+        }
+    }
+}
+
+/// Gets the object that faces are currently being added to.
+fn current_object(objects: &mut Vec<ObjectData>) -> &mut ObjectData {
+    if objects.is_empty() {
+        objects.push(ObjectData::new(UNNAMED_OBJECT.to_owned()));
+    }
+
+    objects.last_mut().unwrap()
+}
+
+fn split_words(line: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (offset, c) in line.char_indices().chain(Some((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(offset),
+            (Some(word_start), true) => {
+                let column = line[..word_start].chars().count() + 1;
+                words.push(Word { text: &line[word_start..offset], column });
+                start = None;
+            },
+            _ => (),
+        }
+    }
+
+    words
+}
+
+/// Converts a path written in a file into a native path.
+///
+/// Files written on Windows often separate directories with backslashes,
+/// which are replaced by forward slashes that work on every platform.
+fn normalize_path(path: &str) -> PathBuf {
+    PathBuf::from(path.replace('\\', "/"))
+}
+
+/// Joins the arguments of a statement back together, for names that may contain spaces.
+fn rest_of_line(arguments: &[Word]) -> String {
+    arguments.iter().map(|word| word.text).collect::<Vec<_>>().join(" ")
+}
+
+fn parse_floats(line: usize, keyword: Word, arguments: &[Word], min: usize, max: usize) -> Result<Vec<f32>, Diagnostic> {
+    if arguments.len() < min || arguments.len() > max {
+        let expected = if min == max { min.to_string() } else { format!("{} to {}", min, max) };
+        return Err(Diagnostic {
+            line,
+            column: keyword.column,
+            message: format!("expected {} numbers but found {}", expected, arguments.len()),
+        });
+    }
+
+    arguments.iter().map(|word| {
+        word.text.parse().map_err(|_| Diagnostic { line, column: word.column, message: format!("invalid number '{}'", word.text) })
+    }).collect()
+}
+
+fn parse_vector(line: usize, keyword: Word, arguments: &[Word], min: usize, max: usize) -> Result<Vector, Diagnostic> {
+    let values = parse_floats(line, keyword, arguments, min, max)?;
+    Ok(Vector(values[0], values[1], values[2]))
+}
+
+fn parse_color(line: usize, keyword: Word, arguments: &[Word]) -> Result<Color, Diagnostic> {
+    let values = parse_floats(line, keyword, arguments, 3, 3)?;
+    Ok(Color(values[0], values[1], values[2]))
+}
+
+fn parse_corners(line: usize, arguments: &[Word], wavefront: &Wavefront) -> Result<Vec<Corner>, Diagnostic> {
+    arguments.iter().map(|word| parse_corner(line, word, wavefront)).collect()
+}
+
+/// Parses a texture map statement, made of options followed by a file name.
+///
+/// If the directory of the material file is known, the texture is resolved
+/// relative to it and a warning is given if the file does not exist.
+fn parse_texture(reporter: &mut Reporter,
+                 line: usize,
+                 keyword: Word,
+                 arguments: &[Word],
+                 directory: Option<&Path>) -> Result<TextureMap, Diagnostic> {
+    let mut texture = TextureMap::new(String::new());
+    let mut words = arguments;
+
+    while let Some((option, rest)) = words.split_first() {
+        if !option.text.starts_with('-') { break }
+        words = rest;
+
+        // Takes up to `max` numbers following the option.
+        let mut numbers = |max: usize| -> Result<Vec<f32>, Diagnostic> {
+            let count = words.iter().take(max).take_while(|word| word.text.parse::<f32>().is_ok()).count();
+            let values = parse_floats(line, *option, &words[..count], 1, max)?;
+            words = &words[count..];
+            Ok(values)
+        };
+        let vector = |values: Vec<f32>, default: f32| {
+            Vector(values[0], *values.get(1).unwrap_or(&default), *values.get(2).unwrap_or(&default))
+        };
+
+        match option.text {
+            "-s" => texture.scale = vector(numbers(3)?, 1.0),
+            "-o" => texture.offset = vector(numbers(3)?, 0.0),
+            "-bm" => texture.bump_multiplier = numbers(1)?[0],
+            "-clamp" => texture.clamp = parse_switch(line, option, &mut words)?,
+            "-blendu" => texture.blend_u = parse_switch(line, option, &mut words)?,
+            "-blendv" => texture.blend_v = parse_switch(line, option, &mut words)?,
+            "-imfchan" => {
+                let (value, rest) = words.split_first().ok_or_else(|| Diagnostic {
+                    line,
+                    column: option.column,
+                    message: "expected a channel".to_owned(),
+                })?;
+                texture.channel = Some(match value.text {
+                    "r" => Channel::Red,
+                    "g" => Channel::Green,
+                    "b" => Channel::Blue,
+                    "m" => Channel::Matte,
+                    "l" => Channel::Luminance,
+                    "z" => Channel::Depth,
+                    other => return Err(Diagnostic { line, column: value.column, message: format!("invalid channel '{}'", other) }),
+                });
+                words = rest;
+            },
+            other => {
+                // Skip the arguments of options that are known but not represented.
+                let argument_count = match other {
+                    "-t" => words.iter().take(3).take_while(|word| word.text.parse::<f32>().is_ok()).count(),
+                    "-mm" => 2,
+                    "-texres" | "-boost" | "-cc" | "-type" => 1,
+                    _ => 0,
+                };
+                words = &words[argument_count.min(words.len())..];
+                let message = format!("unsupported texture option '{}'", other);
+                reporter.warn(WarningKind::Unsupported, Diagnostic { line, column: option.column, message });
+            },
+        }
+    }
+
+    if words.is_empty() {
+        return Err(Diagnostic { line, column: keyword.column, message: "expected a texture file name".to_owned() });
+    }
+
+    texture.path = rest_of_line(words);
+
+    if let Some(directory) = directory {
+        let resolved = directory.join(normalize_path(&texture.path));
+        if !resolved.is_file() {
+            let message = format!("texture '{}' not found", resolved.display());
+            reporter.warn(WarningKind::MissingTexture(resolved.clone()), Diagnostic { line, column: words[0].column, message });
+        }
+        texture.resolved_path = Some(resolved);
+    }
+
+    Ok(texture)
+}
+
+/// Parses the `on` or `off` argument of a texture option.
+fn parse_switch(line: usize, option: &Word, words: &mut &[Word]) -> Result<bool, Diagnostic> {
+    let value = match words.first() {
+        Some(word) if word.text == "on" => true,
+        Some(word) if word.text == "off" => false,
+        Some(word) => return Err(Diagnostic { line, column: word.column, message: format!("expected 'on' or 'off' but found '{}'", word.text) }),
+        None => return Err(Diagnostic { line, column: option.column, message: "expected 'on' or 'off'".to_owned() }),
+    };
+
+    *words = &words[1..];
+    Ok(value)
+}
+
+/// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner.
+fn parse_corner(line: usize, word: &Word, wavefront: &Wavefront) -> Result<Corner, Diagnostic> {
+    let error = |message: String| Diagnostic { line, column: word.column, message };
+    let mut parts = word.text.split('/');
+
+    let mut index = |kind: &str, count: usize, required: bool| -> Result<Option<usize>, Diagnostic> {
+        match parts.next() {
+            Some("") | None if !required => Ok(None),
+            Some(text) => {
+                let index: i64 = text.parse().map_err(|_| error(format!("invalid {} index '{}'", kind, text)))?;
+
+                // Indices are one-based, and negative indices count back from the end.
+                let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+                if index == 0 || resolved < 0 || resolved >= count as i64 {
+                    return Err(error(format!("{} index {} is out of range", kind, index)));
+                }
+                Ok(Some(resolved as usize))
+            },
+            None => Err(error(format!("missing {} index", kind))),
+        }
+    };
+
+    let position = index("vertex", wavefront.positions.len(), true)?.unwrap();
+    let texture_coords = index("texture coordinate", wavefront.texture_coords.len(), false)?;
+    let normal = index("normal", wavefront.normals.len(), false)?;
+
+    if parts.next().is_some() {
+        return Err(error(format!("too many indices in '{}'", word.text)));
+    }
+
+    Ok(Corner { position, texture_coords, normal })
+}
@@ -0,0 +1,104 @@
+//! Splitting polygons into triangles.
+
+use Vector;
+
+/// How polygons with more than three corners are split into triangles.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Triangulation {
+    /// Connects every corner to the first corner.
+    ///
+    /// This is fast, and correct for convex polygons.
+    #[default]
+    Fan,
+    /// Repeatedly cuts off triangles that lie entirely inside the polygon.
+    ///
+    /// This also handles concave polygons. Polygons that cannot be clipped,
+    /// such as self-intersecting or degenerate ones, fall back to a fan.
+    EarClipping,
+}
+
+impl Triangulation {
+    /// Splits a polygon into triangles of corner indices, keeping its winding.
+    pub(crate) fn triangulate(self, positions: &[Vector]) -> Vec<[usize; 3]> {
+        let triangles = match self {
+            Triangulation::Fan => None,
+            Triangulation::EarClipping => ear_clip(positions),
+        };
+
+        triangles.unwrap_or_else(|| fan(positions.len()))
+    }
+}
+
+fn fan(corner_count: usize) -> Vec<[usize; 3]> {
+    (1..corner_count.saturating_sub(1)).map(|i| [0, i, i + 1]).collect()
+}
+
+fn ear_clip(positions: &[Vector]) -> Option<Vec<[usize; 3]>> {
+    if positions.len() <= 3 {
+        return None;
+    }
+
+    let points = project(positions)?;
+    let area: f32 = (0..points.len()).map(|i| cross(points[i], points[(i + 1) % points.len()])).sum();
+    if area == 0.0 {
+        return None;
+    }
+    // Ears turn the same way as the polygon itself.
+    let orientation = area.signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let (a, b, c) = (remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+
+            turn(pa, pb, pc) * orientation > 0.0 && remaining.iter().all(|&other| {
+                other == a || other == b || other == c || !contains(pa, pb, pc, points[other], orientation)
+            })
+        })?;
+
+        triangles.push([remaining[(ear + count - 1) % count], remaining[ear], remaining[(ear + 1) % count]]);
+        remaining.remove(ear);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    Some(triangles)
+}
+
+/// Flattens a polygon onto the axis plane it is most closely aligned with.
+fn project(positions: &[Vector]) -> Option<Vec<(f32, f32)>> {
+    // Newell's method gives a normal that is robust for non-planar polygons.
+    let mut normal = Vector(0.0, 0.0, 0.0);
+    for (i, a) in positions.iter().enumerate() {
+        let b = positions[(i + 1) % positions.len()];
+        normal.0 += (a.1 - b.1) * (a.2 + b.2);
+        normal.1 += (a.2 - b.2) * (a.0 + b.0);
+        normal.2 += (a.0 - b.0) * (a.1 + b.1);
+    }
+
+    let (x, y, z) = (normal.0.abs(), normal.1.abs(), normal.2.abs());
+    if x == 0.0 && y == 0.0 && z == 0.0 {
+        return None;
+    }
+
+    Some(positions.iter().map(|p| {
+        if z >= x && z >= y { (p.0, p.1) } else if x >= y { (p.1, p.2) } else { (p.2, p.0) }
+    }).collect())
+}
+
+fn cross(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+/// Twice the signed area of a triangle.
+fn turn(a: (f32, f32), b: (f32, f32), c: (f32, f32)) -> f32 {
+    cross((b.0 - a.0, b.1 - a.1), (c.0 - a.0, c.1 - a.1))
+}
+
+/// Checks whether a point is inside or on the edge of a triangle.
+fn contains(a: (f32, f32), b: (f32, f32), c: (f32, f32), p: (f32, f32), orientation: f32) -> bool {
+    turn(a, b, p) * orientation >= 0.0 && turn(b, c, p) * orientation >= 0.0 && turn(c, a, p) * orientation >= 0.0
+}
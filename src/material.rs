@@ -0,0 +1,106 @@
+//! Format-independent material representation.
+
+use Color;
+
+use std::path::PathBuf;
+
+/// The surface properties of part of a model.
+///
+/// Materials use the metallic-roughness model of physically based
+/// rendering. Loaders for formats with other lighting models convert
+/// their parameters to the closest match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    /// The name of the material, which may be empty.
+    pub name: String,
+    /// The diffuse or base color.
+    pub base_color: Color,
+    /// The opacity, where `1.0` is fully opaque.
+    pub opacity: f32,
+    /// The emitted light.
+    pub emissive: Color,
+    /// How metallic the surface is, from `0.0` to `1.0`.
+    pub metallic: f32,
+    /// How rough the surface is, from `0.0` to `1.0`.
+    pub roughness: f32,
+    /// How the opacity is used when rendering.
+    pub alpha_mode: AlphaMode,
+    /// Whether back faces should be rendered.
+    pub double_sided: bool,
+    pub base_color_texture: Option<Texture>,
+    /// The texture that `opacity` is multiplied by, if separate from the base color.
+    pub opacity_texture: Option<Texture>,
+    pub emissive_texture: Option<Texture>,
+    /// The metallic texture.
+    ///
+    /// glTF stores metallic and roughness in the blue and green channels of
+    /// a single texture, which is used for both.
+    pub metallic_texture: Option<Texture>,
+    pub roughness_texture: Option<Texture>,
+    pub normal_texture: Option<Texture>,
+    pub occlusion_texture: Option<Texture>,
+}
+
+/// How the opacity of a material is used.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// The opacity is ignored.
+    Opaque,
+    /// Fragments are discarded if their opacity is less than the cutoff.
+    Mask { cutoff: f32 },
+    /// The material is blended with what is behind it.
+    Blend,
+}
+
+/// A reference to a texture image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    /// Where the image is stored.
+    pub source: ImageSource,
+    /// The index of the set of texture coordinates the texture uses.
+    pub texture_coords: u32,
+}
+
+/// Where a texture image is stored.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageSource {
+    /// A file on disk.
+    Path(PathBuf),
+    /// A URI that could not be resolved to a file, such as a `data:` URI.
+    Uri(String),
+    /// Image data stored inside the model file.
+    Embedded {
+        mime_type: String,
+        data: Vec<u8>,
+    },
+}
+
+impl Material {
+    /// Creates an opaque white material.
+    pub fn new(name: String) -> Self {
+        Material {
+            name,
+            base_color: Color(1.0, 1.0, 1.0),
+            opacity: 1.0,
+            emissive: Color(0.0, 0.0, 0.0),
+            metallic: 0.0,
+            roughness: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+            base_color_texture: None,
+            opacity_texture: None,
+            emissive_texture: None,
+            metallic_texture: None,
+            roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+        }
+    }
+}
+
+impl Texture {
+    /// Creates a reference to an image file that uses the first set of texture coordinates.
+    pub fn from_path<P: Into<PathBuf>>(path: P) -> Self {
+        Texture { source: ImageSource::Path(path.into()), texture_coords: 0 }
+    }
+}
@@ -1,14 +1,39 @@
 //! Format-independent mesh representation.
-use {Vertex, Index, Triangle, Error};
+use {Vertex, Index, Triangle, Material, Bounds, Vector, Color, Error};
 
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
 use std::iter::FromIterator;
+use std::ops::Range;
 use std::fmt;
 
 /// A 3D model.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Model<V: Vertex, I: Index> {
     /// The mesh that makes up the model.
     pub mesh: TriangularMesh<V, I>,
+    /// The material table of the model.
+    pub materials: Vec<Material>,
+    /// Named ranges of the index list, such as objects or material groups.
+    ///
+    /// This is empty if the loader does not divide the mesh.
+    pub submeshes: Vec<Submesh>,
+}
+
+/// A named range of a mesh's index list that can be drawn on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Submesh {
+    /// The name of the object the range came from.
+    pub name: String,
+    /// The first index of the range.
+    pub index_offset: usize,
+    /// The number of indices in the range.
+    pub index_count: usize,
+    /// The index of the material in `Model::materials`, if it has one.
+    pub material: Option<usize>,
+    /// The bounds of the vertices used by the range.
+    pub bounds: Bounds,
 }
 
 /// Something which we can build a model out of.
@@ -29,6 +54,19 @@ pub struct TriangularMesh<V: Vertex, I: Index> {
     pub indices: Vec<I>,
 }
 
+/// Builds a mesh out of vertices, merging vertices that are equal.
+///
+/// Vertices keep the order in which they were first seen. Candidates are
+/// found by hashing the attributes that `Vertex` exposes, and then compared
+/// with `PartialEq`, so vertices containing NaN are never merged.
+pub struct MeshBuilder<V: Vertex, I: Index> {
+    mesh: TriangularMesh<V, I>,
+    /// The first vertex with each hash.
+    buckets: HashMap<u64, usize>,
+    /// The next vertex with the same hash as each vertex, if any.
+    next: Vec<Option<usize>>,
+}
+
 /// All of the triangles in a mesh.
 pub struct Triangles<'a, V: Vertex+'a, I: Index+'a>
 {
@@ -39,7 +77,12 @@ pub struct Triangles<'a, V: Vertex+'a, I: Index+'a>
 impl<V: Vertex, I: Index> Model<V,I> {
     /// Creates an empty mesh.
     pub fn empty() -> Self {
-        Model { mesh: TriangularMesh::empty() }
+        Model::from_mesh(TriangularMesh::empty())
+    }
+
+    /// Creates a model out of a mesh, without any materials or submeshes.
+    pub fn from_mesh(mesh: TriangularMesh<V, I>) -> Self {
+        Model { mesh, materials: Vec::new(), submeshes: Vec::new() }
     }
 
     /// Creates a new model.
@@ -53,6 +96,7 @@ impl<V: Vertex, I: Index> fmt::Debug for TriangularMesh<V,I> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         // Create a trait for debug printing.
         #[derive(Debug)]
+        #[allow(dead_code)]
         struct TriangularMesh {
             pub vertex_count: usize,
             pub index_count: usize,
@@ -72,31 +116,137 @@ impl<V: Vertex, I: Index> TriangularMesh<V,I> {
     }
 
     /// Gets all of the triangles in a mesh.
-    pub fn triangles(&self) -> Triangles<V,I> {
+    pub fn triangles(&self) -> Triangles<'_, V, I> {
         Triangles { mesh: self, indices: self.indices.iter() }
     }
+
+    /// Gets the bounds of the vertices used by a range of the index list.
+    ///
+    /// An empty range has empty bounds at the origin.
+    pub fn bounds(&self, indices: Range<usize>) -> Bounds {
+        let positions = self.indices[indices].iter().map(|&index| {
+            let index: u64 = index.into();
+            self.vertices[index as usize].position()
+        });
+
+        Bounds::from_points(positions).unwrap_or(Bounds { min: Vector(0.0, 0.0, 0.0), max: Vector(0.0, 0.0, 0.0) })
+    }
+
+    /// Reverses the winding of every triangle in a range of the index list.
+    ///
+    /// Transforms that mirror the mesh turn its triangles inside out, which
+    /// this undoes.
+    pub fn flip_winding(&mut self, indices: Range<usize>) {
+        for triangle in self.indices[indices].chunks_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
 }
 
+impl<V: Vertex, I: Index> TriangularMesh<V,I> {
+    /// Builds a mesh out of triangles, merging vertices that are equal.
+    ///
+    /// Fails with `ErrorKind::IndexTooSmall` if there are more distinct
+    /// vertices than the index type can address.
+    pub fn try_from_triangles<T>(triangles: T) -> Result<Self, Error>
+        where T: IntoIterator<Item=Triangle<V>> {
+        let triangles = triangles.into_iter();
+        let mut builder = MeshBuilder::with_capacity(triangles.size_hint().0 * 3);
+
+        for triangle in triangles {
+            builder.push_triangle(triangle)?;
+        }
+        Ok(builder.finish())
+    }
+}
+
+/// Builds a mesh out of triangles, merging vertices that are equal.
+///
+/// # Panics
+///
+/// Panics if there are more distinct vertices than the index type can
+/// address. Use `TriangularMesh::try_from_triangles` to handle that case.
 impl<V,I> FromIterator<Triangle<V>> for TriangularMesh<V,I>
     where V: Vertex, I: Index
 {
     fn from_iter<T>(iter: T) -> Self
         where T: IntoIterator<Item=Triangle<V>> {
-        let triangles: Vec<_> = iter.into_iter().collect();
+        TriangularMesh::try_from_triangles(iter).unwrap_or_else(|e| panic!("could not build mesh: {}", e))
+    }
+}
+
+impl<V: Vertex, I: Index> MeshBuilder<V,I> {
+    /// Creates a builder for an empty mesh.
+    pub fn new() -> Self {
+        MeshBuilder::with_capacity(0)
+    }
 
-        let mut vertices: Vec<_> = triangles.iter().flat_map(|tri| tri.vertices.iter().cloned()).collect();
-        vertices.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        vertices.dedup();
+    /// Creates a builder with room for a number of vertices.
+    pub fn with_capacity(vertices: usize) -> Self {
+        MeshBuilder {
+            mesh: TriangularMesh { vertices: Vec::with_capacity(vertices), indices: Vec::with_capacity(vertices) },
+            buckets: HashMap::with_capacity(vertices),
+            next: Vec::with_capacity(vertices),
+        }
+    }
 
-        let indices = triangles.iter().flat_map(|tri| {
-            tri.vertices.iter().map(|vert| {
-                let index: u64 = vertices.binary_search_by(|a| a.partial_cmp(vert).unwrap()).unwrap() as u64;
-                I::from_u64(index).expect("index type too small for mesh")
-            })
-        }).collect();
+    /// Gets the index of a vertex, adding it if no equal vertex has been added.
+    pub fn insert(&mut self, vertex: V) -> Result<I, Error> {
+        let hash = vertex_hash(&vertex);
 
-        TriangularMesh { vertices: vertices, indices: indices }
+        let mut candidate = self.buckets.get(&hash).cloned();
+        while let Some(index) = candidate {
+            if self.mesh.vertices[index] == vertex {
+                return I::from_u64(index as u64);
+            }
+            candidate = self.next[index];
+        }
+
+        let index = self.mesh.vertices.len();
+        let id = I::from_u64(index as u64)?;
+        self.next.push(self.buckets.insert(hash, index));
+        self.mesh.vertices.push(vertex);
+        Ok(id)
     }
+
+    /// Adds a triangle to the mesh.
+    pub fn push_triangle(&mut self, triangle: Triangle<V>) -> Result<(), Error> {
+        let [a, b, c] = triangle.vertices;
+        let indices = [self.insert(a)?, self.insert(b)?, self.insert(c)?];
+        self.mesh.indices.extend_from_slice(&indices);
+        Ok(())
+    }
+
+    /// Gets the mesh that has been built.
+    pub fn finish(self) -> TriangularMesh<V,I> {
+        self.mesh
+    }
+}
+
+impl<V: Vertex, I: Index> Default for MeshBuilder<V,I> {
+    fn default() -> Self {
+        MeshBuilder::new()
+    }
+}
+
+/// Hashes the attributes of a vertex, so that equal vertices have equal hashes.
+fn vertex_hash<V: Vertex>(vertex: &V) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    // Negative zero equals zero, so it must hash the same.
+    let mut write = |value: f32| (if value == 0.0 { 0 } else { value.to_bits() }).hash(&mut hasher);
+
+    let Vector(x, y, z) = vertex.position();
+    write(x); write(y); write(z);
+    if let Some(Vector(x, y, z)) = vertex.normal() {
+        write(x); write(y); write(z);
+    }
+    if let Some(uv) = vertex.texture_coords() {
+        uv.iter().cloned().for_each(&mut write);
+    }
+    if let Some(Color(r, g, b)) = vertex.color() {
+        write(r); write(g); write(b);
+    }
+    hasher.finish()
 }
 
 impl<'a, V: Vertex+'a, I: Index+'a> Iterator for Triangles<'a, V, I> {
@@ -123,7 +273,7 @@ impl<'a, V: Vertex+'a, I: Index+'a> Iterator for Triangles<'a, V, I> {
 
 #[cfg(test)]
 mod test {
-    use {TriangularMesh, Vector, Triangle};
+    use {TriangularMesh, Vector, Triangle, Error, ErrorKind};
     use build;
 
     #[test]
@@ -132,6 +282,43 @@ mod test {
         assert_eq!(cube.triangles().count(), 12);
     }
 
+    #[test]
+    fn welds_in_first_seen_order() {
+        let (a, b, c, d) = (Vector(3.0, 0.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(2.0, 0.0, 0.0), Vector(0.0, -0.0, 0.0));
+        let triangles = vec![
+            Triangle { vertices: [a, b, c] },
+            Triangle { vertices: [c, b, d] },
+            Triangle { vertices: [Vector(0.0, 0.0, 0.0), a, d] },
+        ];
+
+        let mesh: TriangularMesh<_, u8> = triangles.into_iter().collect();
+        assert_eq!(mesh.vertices, vec![a, b, c, d]);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 2, 1, 3, 3, 0, 3]);
+    }
+
+    #[test]
+    fn does_not_panic_on_nan() {
+        let nan = Vector(f32::NAN, 0.0, 0.0);
+        let triangles = vec![Triangle { vertices: [nan, nan, Vector(1.0, 1.0, 1.0)] }];
+
+        let mesh: TriangularMesh<_, u16> = triangles.into_iter().collect();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn reports_index_overflow() {
+        let triangles = (0..100).map(|i| {
+            let x = i as f32;
+            Triangle { vertices: [Vector(x, 0.0, 0.0), Vector(x, 1.0, 0.0), Vector(x, 0.0, 1.0)] }
+        });
+
+        match TriangularMesh::<Vector, u8>::try_from_triangles(triangles) {
+            Err(Error(ErrorKind::IndexTooSmall(256, 8), _)) => (),
+            result => panic!("expected the index type to be too small, got {:?}", result.map(|m| m.vertices.len())),
+        }
+    }
+
     #[test]
     fn can_build_out_of_triangles() {
         let triangles = vec![
@@ -0,0 +1,174 @@
+//! Post-transform vertex cache optimization with Tipsify.
+//!
+//! This is the algorithm from "Fast Triangle Reordering for Vertex Locality
+//! and Reduced Overdraw" by Sander, Nehab and Barczak. It fans out around
+//! one vertex at a time, and picks the next vertex to fan around from the
+//! ones that were just used, preferring ones that will still be in the
+//! cache once their remaining triangles are drawn.
+
+use {TriangularMesh, Vertex, Index};
+use super::{read_indices, write_indices};
+
+use std::ops::Range;
+
+/// Reorders the triangles of an index list, which uses vertices below
+/// `vertex_count`.
+fn tipsify(indices: &[usize], vertex_count: usize, cache_size: usize) -> Vec<usize> {
+    if indices.is_empty() {
+        return Vec::new();
+    }
+    let triangle_count = indices.len() / 3;
+
+    // The triangles that use each vertex.
+    let mut offsets = vec![0; vertex_count + 1];
+    for &vertex in indices {
+        offsets[vertex + 1] += 1;
+    }
+    for vertex in 0..vertex_count {
+        offsets[vertex + 1] += offsets[vertex];
+    }
+    let mut adjacency = vec![0; indices.len()];
+    let mut filled = offsets.clone();
+    for (corner, &vertex) in indices.iter().enumerate() {
+        adjacency[filled[vertex]] = corner / 3;
+        filled[vertex] += 1;
+    }
+
+    let mut live: Vec<usize> = (0..vertex_count).map(|v| offsets[v + 1] - offsets[v]).collect();
+    let mut timestamps = vec![0; vertex_count];
+    let mut emitted = vec![false; triangle_count];
+    let mut dead_ends = Vec::new();
+    let mut output = Vec::with_capacity(indices.len());
+
+    let mut time = cache_size + 1;
+    // Start from the first vertex that has triangles to draw.
+    let mut cursor = live.iter().position(|&count| count > 0).expect("a triangle uses some vertex");
+    let mut fanning = Some(cursor);
+
+    while let Some(vertex) = fanning {
+        let mut candidates = Vec::new();
+
+        for &triangle in &adjacency[offsets[vertex]..offsets[vertex + 1]] {
+            if emitted[triangle] {
+                continue;
+            }
+            emitted[triangle] = true;
+
+            for &v in &indices[triangle * 3..triangle * 3 + 3] {
+                output.push(v);
+                dead_ends.push(v);
+                if !candidates.contains(&v) {
+                    candidates.push(v);
+                }
+                live[v] -= 1;
+
+                if time - timestamps[v] > cache_size {
+                    timestamps[v] = time;
+                    time += 1;
+                }
+            }
+        }
+
+        // Prefer the candidate that has been in the cache the longest, as
+        // long as fanning around it would not push it out.
+        let mut best = None;
+        let mut best_priority = None;
+        for &v in &candidates {
+            if live[v] == 0 {
+                continue;
+            }
+            let priority = if time - timestamps[v] + 2 * live[v] <= cache_size { time - timestamps[v] } else { 0 };
+            if best_priority.is_none_or(|best| priority > best) {
+                best = Some(v);
+                best_priority = Some(priority);
+            }
+        }
+
+        fanning = best.or_else(|| {
+            // Go back to a recently used vertex, or else to the next one in
+            // input order, that still has triangles to draw.
+            while let Some(v) = dead_ends.pop() {
+                if live[v] > 0 {
+                    return Some(v);
+                }
+            }
+            while cursor < vertex_count {
+                if live[cursor] > 0 {
+                    return Some(cursor);
+                }
+                cursor += 1;
+            }
+            None
+        });
+    }
+
+    output
+}
+
+impl<V: Vertex, I: Index> TriangularMesh<V,I> {
+    /// Reorders the triangles of a range of the index list so that vertices
+    /// are reused while they are still in a post-transform cache of a
+    /// number of vertices.
+    ///
+    /// Every triangle keeps its winding, and no triangles leave the range.
+    pub fn optimize_vertex_cache(&mut self, indices: Range<usize>, cache_size: usize) {
+        let range = &mut self.indices[indices];
+        let reordered = tipsify(&read_indices(range), self.vertices.len(), cache_size);
+        write_indices(range, &reordered);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use optimize::test::{scattered_grid, sorted_triangles};
+    use build;
+    use Vector;
+
+    #[test]
+    fn reduces_cache_misses() {
+        let mut mesh = scattered_grid(32);
+        let original = mesh.clone();
+        let count = mesh.indices.len();
+        let before = mesh.cache_statistics(0..count, 16);
+
+        mesh.optimize_vertex_cache(0..count, 16);
+        let after = mesh.cache_statistics(0..count, 16);
+        assert!(before.acmr > 2.0);
+        assert!(after.acmr < 0.8, "{:?}", after);
+        assert!(after.atvr < before.atvr);
+        assert_eq!(sorted_triangles(&mesh, 0..count), sorted_triangles(&original, 0..count));
+    }
+
+    #[test]
+    fn only_reorders_within_the_range() {
+        let mut mesh: TriangularMesh<Vector, u32> = build::unit_cube();
+        let original = mesh.clone();
+
+        mesh.optimize_vertex_cache(18..36, 4);
+        assert_eq!(&mesh.indices[..18], &original.indices[..18]);
+        assert_eq!(sorted_triangles(&mesh, 18..36), sorted_triangles(&original, 18..36));
+    }
+
+    #[test]
+    fn leaves_empty_meshes_alone() {
+        let mut mesh: TriangularMesh<Vector, u32> = TriangularMesh::empty();
+        mesh.optimize_vertex_cache(0..0, 16);
+        assert!(mesh.indices.is_empty());
+
+        let mut model = ::Model::from_mesh(mesh);
+        assert!(model.optimize(&::optimize::Options::default()).is_empty());
+    }
+
+    #[test]
+    fn starts_from_the_first_used_vertex() {
+        let mut cube: TriangularMesh<Vector, u32> = build::unit_cube();
+        cube.vertices.insert(0, Vector(5.0, 5.0, 5.0));
+        for index in cube.indices.iter_mut() {
+            *index += 1;
+        }
+        let original = cube.clone();
+        cube.optimize_vertex_cache(0..36, 16);
+        assert_eq!(sorted_triangles(&cube, 0..36), sorted_triangles(&original, 0..36));
+    }
+}
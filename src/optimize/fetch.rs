@@ -0,0 +1,63 @@
+//! Reordering the vertex buffer into the order it is read.
+
+use {TriangularMesh, Vertex, Index};
+use super::{read_indices, write_indices};
+
+impl<V: Vertex, I: Index> TriangularMesh<V,I> {
+    /// Reorders the vertices into the order in which the index list first
+    /// uses them, returning the new index of every old vertex.
+    ///
+    /// This keeps memory reads close together once the triangles are in
+    /// their final order. Vertices that no triangle uses are kept, after
+    /// all of the used ones.
+    pub fn optimize_vertex_fetch(&mut self) -> Vec<usize> {
+        let mut indices = read_indices(&self.indices);
+        let mut remap = vec![None; self.vertices.len()];
+        let mut order = Vec::with_capacity(self.vertices.len());
+
+        for &vertex in &indices {
+            if remap[vertex].is_none() {
+                remap[vertex] = Some(order.len());
+                order.push(vertex);
+            }
+        }
+        for (vertex, new) in remap.iter_mut().enumerate() {
+            if new.is_none() {
+                *new = Some(order.len());
+                order.push(vertex);
+            }
+        }
+        let remap: Vec<usize> = remap.into_iter().map(|new| new.expect("every vertex is placed")).collect();
+
+        let mut old: Vec<Option<V>> = self.vertices.drain(..).map(Some).collect();
+        self.vertices = order.into_iter().map(|vertex| old[vertex].take().expect("every vertex is placed once")).collect();
+
+        for index in indices.iter_mut() {
+            *index = remap[*index];
+        }
+        write_indices(&mut self.indices, &indices);
+
+        remap
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {TriangularMesh, Vector};
+
+    #[test]
+    fn orders_vertices_by_first_use() {
+        let vertex = |x: f32| Vector(x, 0.0, 0.0);
+        let mut mesh: TriangularMesh<Vector, u16> = TriangularMesh {
+            vertices: vec![vertex(0.0), vertex(1.0), vertex(2.0), vertex(3.0), vertex(4.0)],
+            indices: vec![3, 1, 4, 1, 0, 4],
+        };
+        let original: Vec<_> = mesh.triangles().collect();
+
+        let remap = mesh.optimize_vertex_fetch();
+        assert_eq!(remap, vec![3, 1, 4, 0, 2]);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 1, 3, 2]);
+        assert_eq!(mesh.vertices, vec![vertex(3.0), vertex(1.0), vertex(4.0), vertex(0.0), vertex(2.0)]);
+        assert_eq!(mesh.triangles().collect::<Vec<_>>(), original);
+    }
+}
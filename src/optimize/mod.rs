@@ -0,0 +1,200 @@
+//! Reordering meshes so that GPUs draw them faster.
+//!
+//! Meshes come out of most files with their triangles in authoring order,
+//! which makes the GPU transform shared vertices many times. The passes
+//! here are meant to be run in order:
+//!
+//! 1. `TriangularMesh::optimize_vertex_cache` reorders triangles so that
+//!    shared vertices are still in the post-transform cache when they are
+//!    used again.
+//! 2. `TriangularMesh::optimize_overdraw` reorders clusters of those
+//!    triangles so that surfaces facing outwards are drawn first, without
+//!    giving up much of the cache efficiency.
+//! 3. `TriangularMesh::optimize_vertex_fetch` reorders the vertex buffer
+//!    into the order in which it is read.
+//!
+//! `Model::optimize` runs all three on every submesh, and
+//! `TriangularMesh::cache_statistics` measures the result.
+//!
+//! ```
+//! use mash::{build, optimize, Model, Vector};
+//!
+//! let mut model: Model<Vector, u16> = Model::from_mesh(build::unit_cube());
+//! let before = model.mesh.cache_statistics(0..model.mesh.indices.len(), 16);
+//!
+//! model.optimize(&optimize::Options::default());
+//! let after = model.mesh.cache_statistics(0..model.mesh.indices.len(), 16);
+//! assert!(after.acmr <= before.acmr);
+//! ```
+
+pub use self::stats::CacheStatistics;
+
+mod stats;
+mod cache;
+mod overdraw;
+mod fetch;
+
+use {Model, Vertex, Index};
+
+/// How `Model::optimize` reorders a model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Options {
+    /// The number of vertices that the post-transform cache holds.
+    pub cache_size: usize,
+    /// How much worse the average cache miss ratio of a cluster may get to
+    /// reduce overdraw, as a ratio, or `None` to leave overdraw alone.
+    pub overdraw_threshold: Option<f32>,
+}
+
+impl Default for Options {
+    /// Models a 16-entry cache, and allows 5% more cache misses to reduce
+    /// overdraw.
+    fn default() -> Self {
+        Options {
+            cache_size: 16,
+            overdraw_threshold: Some(1.05),
+        }
+    }
+}
+
+/// A first-in-first-out post-transform cache.
+struct FifoCache {
+    /// When each vertex was last added to the cache.
+    timestamps: Vec<usize>,
+    time: usize,
+    size: usize,
+}
+
+impl FifoCache {
+    fn new(vertex_count: usize, size: usize) -> Self {
+        FifoCache { timestamps: vec![0; vertex_count], time: size + 1, size }
+    }
+
+    /// Uses a vertex, returning whether it had to be transformed.
+    fn miss(&mut self, vertex: usize) -> bool {
+        if self.time - self.timestamps[vertex] > self.size {
+            self.timestamps[vertex] = self.time;
+            self.time += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Counts the vertices of a triangle that had to be transformed.
+    fn triangle_misses(&mut self, triangle: &[usize]) -> usize {
+        triangle.iter().filter(|&&vertex| self.miss(vertex)).count()
+    }
+
+    /// Empties the cache.
+    fn clear(&mut self) {
+        self.time += self.size + 1;
+    }
+}
+
+/// Reads a range of an index list.
+fn read_indices<I: Index>(indices: &[I]) -> Vec<usize> {
+    indices.iter().map(|&i| i.into() as usize).collect()
+}
+
+/// Writes indices that were read with `read_indices` back to an index list.
+fn write_indices<I: Index>(indices: &mut [I], values: &[usize]) {
+    for (index, &value) in indices.iter_mut().zip(values) {
+        *index = I::from_u64(value as u64).expect("reordering never adds vertices");
+    }
+}
+
+impl<V: Vertex, I: Index> Model<V,I> {
+    /// Runs every optimization pass on the model, returning the new index
+    /// of every old vertex.
+    ///
+    /// Triangles are only reordered within their submesh, or within the
+    /// whole mesh if there are no submeshes, so submeshes stay valid.
+    pub fn optimize(&mut self, options: &Options) -> Vec<usize> {
+        let ranges: Vec<(usize, usize)> = if self.submeshes.is_empty() {
+            vec![(0, self.mesh.indices.len())]
+        } else {
+            self.submeshes.iter().map(|s| (s.index_offset, s.index_count)).collect()
+        };
+
+        for (offset, count) in ranges {
+            let range = offset..offset + count;
+            self.mesh.optimize_vertex_cache(range.clone(), options.cache_size);
+            if let Some(threshold) = options.overdraw_threshold {
+                self.mesh.optimize_overdraw(range, options.cache_size, threshold);
+            }
+        }
+
+        self.mesh.optimize_vertex_fetch()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {TriangularMesh, Vector, Submesh, Bounds, Triangle};
+    use std::ops::Range;
+
+    /// A grid of squares with its triangles in a scattered order, as an
+    /// exporter might leave them.
+    pub fn scattered_grid(size: usize) -> TriangularMesh<Vector, u32> {
+        let vertex = |x: usize, y: usize| (y * (size + 1) + x) as u32;
+        let mut triangles = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                triangles.push([vertex(x, y), vertex(x + 1, y), vertex(x + 1, y + 1)]);
+                triangles.push([vertex(x, y), vertex(x + 1, y + 1), vertex(x, y + 1)]);
+            }
+        }
+        // Shuffle the triangles with a fixed xorshift sequence.
+        let mut state = 0x2545_f491_u32;
+        for i in (1..triangles.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            triangles.swap(i, state as usize % (i + 1));
+        }
+        let indices = triangles.iter().flat_map(|t| t.to_vec()).collect();
+
+        let vertices = (0..(size + 1) * (size + 1)).map(|i| Vector((i % (size + 1)) as f32, (i / (size + 1)) as f32, 0.0)).collect();
+        TriangularMesh { vertices, indices }
+    }
+
+    /// Gets the triangles of a range, rotated to start at their lowest
+    /// vertex and sorted, to compare meshes regardless of order.
+    pub fn sorted_triangles<V: Vertex + PartialOrd>(mesh: &TriangularMesh<V, u32>, range: Range<usize>) -> Vec<Triangle<V>> {
+        let mut triangles: Vec<_> = mesh.indices[range].chunks(3).map(|t| {
+            let mut vertices = [mesh.vertices[t[0] as usize].clone(), mesh.vertices[t[1] as usize].clone(), mesh.vertices[t[2] as usize].clone()];
+            let lowest = (0..3).fold(0, |lowest, i| if vertices[i] < vertices[lowest] { i } else { lowest });
+            vertices.rotate_left(lowest);
+            Triangle { vertices }
+        }).collect();
+        triangles.sort_by(|a, b| a.vertices.partial_cmp(&b.vertices).unwrap());
+        triangles
+    }
+
+    #[test]
+    fn optimizes_submeshes_separately() {
+        let mut mesh = scattered_grid(16);
+        let half = mesh.indices.len() / 2;
+        let empty = Bounds { min: Vector(0.0, 0.0, 0.0), max: Vector(0.0, 0.0, 0.0) };
+        let submesh = |index_offset, index_count| Submesh { name: String::new(), index_offset, index_count, material: None, bounds: empty };
+        mesh.vertices.reverse();
+
+        let mut model = Model { mesh, materials: Vec::new(), submeshes: vec![submesh(0, half), submesh(half, half)] };
+        let original = model.clone();
+        let before = model.mesh.cache_statistics(0..half * 2, 16);
+
+        let remap = model.optimize(&Options::default());
+        assert_eq!(remap.len(), model.mesh.vertices.len());
+        for range in [0..half, half..half * 2] {
+            assert_eq!(sorted_triangles(&model.mesh, range.clone()), sorted_triangles(&original.mesh, range));
+        }
+        for (old, &new) in remap.iter().enumerate() {
+            assert_eq!(model.mesh.vertices[new], original.mesh.vertices[old]);
+        }
+
+        let after = model.mesh.cache_statistics(0..half * 2, 16);
+        assert!(after.acmr < before.acmr * 0.6, "{:?} is not much better than {:?}", after, before);
+    }
+}
@@ -0,0 +1,137 @@
+//! Reordering clusters of triangles to reduce overdraw.
+//!
+//! The triangles are split into clusters wherever the cache order already
+//! restarts, and then further wherever a cluster has reached the cache
+//! efficiency that is allowed. Clusters are then drawn starting with the
+//! ones that face away from the middle of the mesh, which tend to hide the
+//! others.
+
+use {TriangularMesh, Vertex, Index, Vector};
+use super::{FifoCache, read_indices, write_indices};
+
+use std::ops::Range;
+
+/// Finds the triangles that start clusters, given a triangle order that is
+/// already optimized for the cache.
+fn clusters(indices: &[usize], vertex_count: usize, cache_size: usize, threshold: f32) -> Vec<usize> {
+    let triangles: Vec<&[usize]> = indices.chunks(3).collect();
+    let mut cache = FifoCache::new(vertex_count, cache_size);
+
+    // A triangle none of whose vertices are cached starts a new patch of
+    // the mesh.
+    let hard: Vec<usize> = (0..triangles.len())
+        .filter(|&t| cache.triangle_misses(triangles[t]) == 3 || t == 0)
+        .collect();
+
+    let mut starts = Vec::new();
+    for (i, &start) in hard.iter().enumerate() {
+        let end = hard.get(i + 1).cloned().unwrap_or(triangles.len());
+
+        cache.clear();
+        let misses: usize = triangles[start..end].iter().map(|t| cache.triangle_misses(t)).sum();
+        let target = threshold * misses as f32 / (end - start) as f32;
+
+        // Split the patch as soon as it is efficient enough on its own.
+        cache.clear();
+        starts.push(start);
+        let (mut running_misses, mut running_triangles) = (0, 0);
+        for (t, triangle) in triangles.iter().enumerate().take(end).skip(start) {
+            running_misses += cache.triangle_misses(triangle);
+            running_triangles += 1;
+
+            if t + 1 < end && running_misses as f32 / running_triangles as f32 <= target {
+                starts.push(t + 1);
+                cache.clear();
+                running_misses = 0;
+                running_triangles = 0;
+            }
+        }
+    }
+    starts
+}
+
+impl<V: Vertex, I: Index> TriangularMesh<V,I> {
+    /// Reorders clusters of triangles in a range of the index list so that
+    /// surfaces facing outwards are drawn first.
+    ///
+    /// This should be run after `optimize_vertex_cache`, with the same cache
+    /// size. The `threshold` is how much worse the average cache miss ratio
+    /// of a cluster is allowed to get, such as 1.05 for 5%, with higher
+    /// values giving smaller clusters and less overdraw.
+    pub fn optimize_overdraw(&mut self, indices: Range<usize>, cache_size: usize, threshold: f32) {
+        let values = read_indices(&self.indices[indices.clone()]);
+        if values.is_empty() {
+            return;
+        }
+        let vertices = &self.vertices;
+        let position = |corner: usize| vertices[values[corner]].position();
+
+        let centroid = (0..values.len()).fold(Vector::ZERO, |sum, corner| sum + position(corner)) / values.len() as f32;
+
+        let starts = clusters(&values, vertices.len(), cache_size, threshold);
+        let ends: Vec<usize> = starts.iter().skip(1).cloned().chain(Some(values.len() / 3)).collect();
+
+        // Clusters that are further out along their normal are more likely
+        // to hide the others.
+        let mut sorted: Vec<(f32, Range<usize>)> = starts.iter().zip(ends.iter()).map(|(&start, &end)| {
+            let (mut middle, mut normal, mut area) = (Vector::ZERO, Vector::ZERO, 0.0);
+            for t in start..end {
+                let (a, b, c) = (position(t * 3), position(t * 3 + 1), position(t * 3 + 2));
+                let cross = (b - a).cross(c - a);
+                let size = cross.length();
+
+                middle += (a + b + c) * (size / 3.0);
+                normal += cross;
+                area += size;
+            }
+            let middle = if area > 0.0 { middle / area } else { position(start * 3) };
+
+            ((middle - centroid).dot(normal.normalize()), start..end)
+        }).collect();
+        sorted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(::std::cmp::Ordering::Equal));
+
+        let reordered: Vec<usize> = sorted.into_iter()
+            .flat_map(|(_, triangles)| values[triangles.start * 3..triangles.end * 3].to_vec())
+            .collect();
+        write_indices(&mut self.indices[indices], &reordered);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use optimize::test::{scattered_grid, sorted_triangles};
+    use {build, load};
+
+    #[test]
+    fn draws_outer_surfaces_first() {
+        let inner: TriangularMesh<load::Vertex, u32> = build::cube_with_normals(1.0);
+        let outer: TriangularMesh<load::Vertex, u32> = build::cube_with_normals(2.0);
+
+        // The inner cube comes first in the file.
+        let mut mesh = inner.clone();
+        let offset = mesh.vertices.len() as u32;
+        mesh.vertices.extend(outer.vertices.iter().cloned());
+        mesh.indices.extend(outer.indices.iter().map(|&i| i + offset));
+        let original = mesh.clone();
+
+        let count = mesh.indices.len();
+        mesh.optimize_vertex_cache(0..count, 16);
+        mesh.optimize_overdraw(0..count, 16, 1.05);
+
+        assert!(mesh.indices[..count / 2].iter().all(|&i| i >= offset), "{:?}", mesh.indices);
+        assert_eq!(sorted_triangles(&mesh, 0..count), sorted_triangles(&original, 0..count));
+    }
+
+    #[test]
+    fn keeps_most_of_the_cache_efficiency() {
+        let mut mesh = scattered_grid(32);
+        let count = mesh.indices.len();
+        mesh.optimize_vertex_cache(0..count, 16);
+        let optimized = mesh.cache_statistics(0..count, 16);
+
+        mesh.optimize_overdraw(0..count, 16, 1.05);
+        let after = mesh.cache_statistics(0..count, 16);
+        assert!(after.acmr <= optimized.acmr * 1.2, "{:?} is much worse than {:?}", after, optimized);
+    }
+}
@@ -0,0 +1,77 @@
+//! Measuring how well a mesh uses the post-transform cache.
+
+use {TriangularMesh, Vertex, Index};
+use super::{FifoCache, read_indices};
+
+use std::ops::Range;
+
+/// How often the vertices of a mesh are transformed when it is drawn.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CacheStatistics {
+    /// The number of triangles drawn.
+    pub triangles: usize,
+    /// The number of distinct vertices used by the triangles.
+    pub vertices: usize,
+    /// The number of times a vertex was not in the cache and had to be
+    /// transformed.
+    pub transformed: usize,
+    /// The average cache miss ratio, which is the number of transformed
+    /// vertices per triangle.
+    ///
+    /// This is between 0.5 for an ideal grid and 3 for no reuse at all.
+    pub acmr: f32,
+    /// The average transform to vertex ratio, which is the number of times
+    /// each vertex was transformed.
+    ///
+    /// This is 1 for an ideal order, and does not depend on how the mesh
+    /// is connected, unlike the ACMR.
+    pub atvr: f32,
+}
+
+impl<V: Vertex, I: Index> TriangularMesh<V,I> {
+    /// Simulates drawing a range of the index list with a first-in-first-out
+    /// cache of a number of vertices.
+    ///
+    /// The ratios are zero for an empty range.
+    pub fn cache_statistics(&self, indices: Range<usize>, cache_size: usize) -> CacheStatistics {
+        let indices = read_indices(&self.indices[indices]);
+        let mut cache = FifoCache::new(self.vertices.len(), cache_size);
+        let transformed = indices.chunks(3).map(|triangle| cache.triangle_misses(triangle)).sum();
+
+        let mut used = vec![false; self.vertices.len()];
+        let vertices = indices.iter().filter(|&&vertex| !::std::mem::replace(&mut used[vertex], true)).count();
+
+        let ratio = |count: usize| if count > 0 { transformed as f32 / count as f32 } else { 0.0 };
+        CacheStatistics {
+            triangles: indices.len() / 3,
+            vertices,
+            transformed,
+            acmr: ratio(indices.len() / 3),
+            atvr: ratio(vertices),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {TriangularMesh, Vector};
+
+    #[test]
+    fn counts_transformed_vertices() {
+        let quad: TriangularMesh<Vector, u16> = TriangularMesh {
+            vertices: vec![Vector(0.0, 0.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(1.0, 1.0, 0.0), Vector(0.0, 1.0, 0.0)],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        };
+        let stats = quad.cache_statistics(0..6, 16);
+        assert_eq!((stats.triangles, stats.vertices, stats.transformed), (2, 4, 4));
+        assert_eq!((stats.acmr, stats.atvr), (2.0, 1.0));
+
+        // Vertex 0 has left a two-vertex cache by the time it is reused.
+        let small = quad.cache_statistics(0..6, 2);
+        assert_eq!(small.transformed, 5);
+        assert_eq!(small.atvr, 1.25);
+
+        let empty = quad.cache_statistics(0..0, 16);
+        assert_eq!((empty.acmr, empty.atvr), (0.0, 0.0));
+    }
+}
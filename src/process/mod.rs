@@ -0,0 +1,58 @@
+//! Passes that process meshes after they have been built.
+
+pub use self::weld::WeldOptions;
+pub use self::normals::{NormalOptions, Weighting};
+
+mod weld;
+mod normals;
+mod tangents;
+
+use {TriangularMesh, Vertex, Index, Error};
+
+/// Sets an attribute of every corner of a mesh, returning the original
+/// index of every vertex.
+///
+/// Each vertex keeps its slot for the first value it is given, and is
+/// copied to the end of the vertex list for every other one. The mesh is
+/// left unchanged if the copies cannot be addressed by the index type.
+fn set_corner_attributes<V, I, T, F, S>(mesh: &mut TriangularMesh<V,I>, values: &[T], same: F, mut set: S)
+    -> Result<Vec<usize>, Error>
+    where V: Vertex, I: Index, T: Copy, F: Fn(&T, &T) -> bool, S: FnMut(&mut V, T) {
+    let mut original: Vec<usize> = (0..mesh.vertices.len()).collect();
+    let mut assigned: Vec<Option<T>> = vec![None; mesh.vertices.len()];
+    let mut variants: Vec<Vec<(T, usize)>> = vec![Vec::new(); mesh.vertices.len()];
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+
+    for (&index, &value) in mesh.indices.iter().zip(values.iter()) {
+        let vertex = index.into() as usize;
+        let index = match variants[vertex].iter().find(|&(v, _)| same(v, &value)) {
+            Some(&(_, index)) => index,
+            None => {
+                let index = if variants[vertex].is_empty() {
+                    vertex
+                } else {
+                    original.push(vertex);
+                    assigned.push(None);
+                    original.len() - 1
+                };
+                assigned[index] = Some(value);
+                variants[vertex].push((value, index));
+                index
+            },
+        };
+        indices.push(I::from_u64(index as u64)?);
+    }
+
+    for (index, value) in assigned.into_iter().enumerate() {
+        if index >= mesh.vertices.len() {
+            let copy = mesh.vertices[original[index]].clone();
+            mesh.vertices.push(copy);
+        }
+        if let Some(value) = value {
+            set(&mut mesh.vertices[index], value);
+        }
+    }
+    mesh.indices = indices;
+
+    Ok(original)
+}
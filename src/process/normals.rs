@@ -0,0 +1,278 @@
+//! Generating vertex normals from the faces of a mesh.
+
+use {TriangularMesh, HasNormal, Vector, Index, Error};
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// How the normals of the faces around a vertex are combined.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Weighting {
+    /// Larger faces pull the normal further towards themselves.
+    Area,
+    /// Faces are weighted by the angle of their corner at the vertex, which
+    /// does not depend on how the surface was triangulated.
+    Angle,
+}
+
+/// How `TriangularMesh::generate_normals` computes normals.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NormalOptions<'a> {
+    /// How the faces around a vertex are combined.
+    pub weighting: Weighting,
+    /// The largest angle between two faces that are smoothed together, in
+    /// radians.
+    ///
+    /// Edges that are sharper than this stay hard. An angle of zero gives
+    /// flat normals, and `PI` smooths every edge.
+    pub crease_angle: f32,
+    /// The smoothing group of every triangle, such as the ones given by
+    /// `load::wavefront::Wavefront::smoothing_groups`.
+    ///
+    /// When set, faces are only smoothed together if they are in the same
+    /// group, and faces with no group are flat.
+    pub smoothing_groups: Option<&'a [Option<u32>]>,
+}
+
+impl<'a> Default for NormalOptions<'a> {
+    /// Smooths every edge, weighting faces by angle.
+    fn default() -> Self {
+        NormalOptions {
+            weighting: Weighting::Angle,
+            crease_angle: PI,
+            smoothing_groups: None,
+        }
+    }
+}
+
+impl<'a> NormalOptions<'a> {
+    /// Options for flat normals, where every face has its own normal.
+    pub fn flat() -> Self {
+        NormalOptions { crease_angle: 0.0, ..NormalOptions::default() }
+    }
+
+    /// Checks whether two faces are smoothed together.
+    fn smooths(&self, a: usize, b: usize, normals: &[Vector]) -> bool {
+        if a == b {
+            return true;
+        }
+        if self.crease_angle <= 0.0 {
+            return false;
+        }
+
+        let groups = self.smoothing_groups.is_none_or(|groups| groups[a].is_some() && groups[a] == groups[b]);
+        let cos = normals[a].dot(normals[b]).clamp(-1.0, 1.0);
+        groups && cos.acos() <= self.crease_angle
+    }
+}
+
+/// The bits of a position, with negative zero counted as zero.
+fn position_key(position: Vector) -> [u32; 3] {
+    let bits = |x: f32| if x == 0.0 { 0 } else { x.to_bits() };
+    [bits(position.0), bits(position.1), bits(position.2)]
+}
+
+impl<V: HasNormal, I: Index> TriangularMesh<V,I> {
+    /// Sets the normal of every vertex from the faces around it, returning
+    /// the original index of every vertex.
+    ///
+    /// Faces are found by position, so vertices that were split for other
+    /// attributes, such as at UV seams, are still smoothed together. A
+    /// vertex whose corners need different normals is split, with the copies
+    /// added to the end of the vertex list.
+    ///
+    /// Fails with `ErrorKind::IndexTooSmall`, leaving the mesh unchanged, if
+    /// the split vertices cannot be addressed by the index type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are smoothing groups for a different number of
+    /// triangles than the mesh has.
+    pub fn generate_normals(&mut self, options: &NormalOptions) -> Result<Vec<usize>, Error> {
+        let triangle_count = self.indices.len() / 3;
+        if let Some(groups) = options.smoothing_groups {
+            assert_eq!(groups.len(), triangle_count, "there must be a smoothing group for every triangle");
+        }
+
+        let corners: Vec<usize> = self.indices.iter().map(|&i| i.into() as usize).collect();
+        let positions: Vec<Vector> = corners.iter().map(|&i| self.vertices[i].position()).collect();
+
+        let mut face_normals = Vec::with_capacity(triangle_count);
+        let mut weights = Vec::with_capacity(corners.len());
+        for triangle in positions.chunks_exact(3) {
+            let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+            let cross = (b - a).cross(c - a);
+            let normal = cross.normalize();
+            face_normals.push(normal);
+
+            match options.weighting {
+                // The cross product is twice the area of the triangle.
+                Weighting::Area => weights.extend_from_slice(&[cross, cross, cross]),
+                Weighting::Angle => {
+                    let angle = |p: Vector, q: Vector, r: Vector| {
+                        let cos = (q - p).normalize().dot((r - p).normalize()).clamp(-1.0, 1.0);
+                        normal * cos.acos()
+                    };
+                    weights.extend_from_slice(&[angle(a, b, c), angle(b, c, a), angle(c, a, b)]);
+                },
+            }
+        }
+
+        let mut shared: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (corner, &position) in positions.iter().enumerate() {
+            shared.entry(position_key(position)).or_default().push(corner);
+        }
+
+        let corner_normals: Vec<Vector> = positions.iter().enumerate().map(|(corner, &position)| {
+            let face = corner / 3;
+            if options.crease_angle <= 0.0 {
+                return face_normals[face];
+            }
+            let sum = shared[&position_key(position)].iter()
+                .filter(|&&other| options.smooths(face, other / 3, &face_normals))
+                .fold(Vector::ZERO, |sum, &other| sum + weights[other]);
+
+            if sum.length_squared() > 0.0 { sum.normalize() } else { face_normals[face] }
+        }).collect();
+
+        // Normals that only differ by float noise share a vertex.
+        super::set_corner_attributes(self, &corner_normals, |a, b| (*a - *b).length() <= 1.0e-6, V::set_normal)
+    }
+
+    /// Gives every face its own normal, splitting vertices that are shared
+    /// between faces facing different ways.
+    pub fn generate_flat_normals(&mut self) -> Result<Vec<usize>, Error> {
+        self.generate_normals(&NormalOptions::flat())
+    }
+
+    /// Smooths the normals across every edge of the mesh.
+    pub fn generate_smooth_normals(&mut self, weighting: Weighting) -> Result<Vec<usize>, Error> {
+        self.generate_normals(&NormalOptions { weighting, ..NormalOptions::default() })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Vertex, ErrorKind};
+    use build;
+    use load;
+
+    fn cube() -> TriangularMesh<load::Vertex, u16> {
+        build::unit_cube()
+    }
+
+    fn assert_close(a: Vector, b: Vector) {
+        assert!((a - b).length() < 1.0e-5, "{:?} is not {:?}", a, b);
+    }
+
+    #[test]
+    fn splits_vertices_for_flat_normals() {
+        let mut mesh = cube();
+        let original = mesh.generate_flat_normals().unwrap();
+
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(original.len(), 24);
+        assert_eq!(&original[..8], &[0, 1, 2, 3, 4, 5, 6, 7]);
+        for (new, &old) in original.iter().enumerate() {
+            assert_eq!(mesh.vertices[new].position, cube().vertices[old].position);
+        }
+        for triangle in mesh.triangles() {
+            let [a, b, c] = triangle.vertices;
+            let face = (b.position - a.position).cross(c.position - a.position).normalize();
+            for vertex in &[a, b, c] {
+                assert_close(vertex.normal.unwrap(), face);
+            }
+        }
+    }
+
+    #[test]
+    fn smooths_with_angle_and_area_weights() {
+        let mut angle = cube();
+        assert_eq!(angle.generate_smooth_normals(Weighting::Angle).unwrap(), (0..8).collect::<Vec<_>>());
+        for vertex in &angle.vertices {
+            assert_close(vertex.normal.unwrap(), vertex.position.normalize());
+        }
+
+        // Corners split by the diagonal of a side count that side twice.
+        let mut area = cube();
+        area.generate_smooth_normals(Weighting::Area).unwrap();
+        assert_eq!(area.vertices.len(), 8);
+        assert!(area.vertices.iter().all(|v| v.normal.unwrap().dot(v.position) > 0.0));
+        assert!(area.vertices.iter().any(|v| (v.normal.unwrap() - v.position.normalize()).length() > 0.1));
+    }
+
+    #[test]
+    fn keeps_edges_sharper_than_the_crease_angle() {
+        let mut hard = cube();
+        hard.generate_normals(&NormalOptions { crease_angle: 60f32.to_radians(), ..NormalOptions::default() }).unwrap();
+        assert_eq!(hard.vertices.len(), 24);
+
+        let mut soft = cube();
+        soft.generate_normals(&NormalOptions { crease_angle: 100f32.to_radians(), ..NormalOptions::default() }).unwrap();
+        assert_eq!(soft.vertices.len(), 8);
+    }
+
+    #[test]
+    fn honors_smoothing_groups() {
+        let mut mesh = cube();
+        // The top side is in its own group and the bottom side is flat.
+        let groups: Vec<_> = mesh.triangles().map(|t| {
+            let y = t.vertices.iter().map(|v| v.position.1).sum::<f32>();
+            if y == 3.0 { Some(2) } else if y == -3.0 { None } else { Some(1) }
+        }).collect();
+
+        mesh.generate_normals(&NormalOptions { smoothing_groups: Some(&groups), ..NormalOptions::default() }).unwrap();
+        assert_eq!(mesh.vertices.len(), 16);
+        for triangle in mesh.triangles() {
+            let heights: Vec<_> = triangle.vertices.iter().map(|v| v.normal.unwrap().1.round()).collect();
+            assert!(heights.iter().all(|&y| y == heights[0]), "{:?}", triangle);
+        }
+    }
+
+    #[cfg(feature = "wavefront")]
+    #[test]
+    fn uses_wavefront_smoothing_groups() {
+        use Model;
+
+        // Two quads folded along the x axis, smoothed together and apart.
+        let obj = |second: &str| format!("v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\nv 1 1 0\nv 0 1 0\n\
+            s 1\nf 1 4 3 2\ns {}\nf 1 2 5 6\n", second);
+        let normals = |second: &str| {
+            let wavefront = load::wavefront::from_memory(&mut obj(second).as_bytes(), |_| &b""[..]).unwrap();
+            let groups = wavefront.smoothing_groups();
+            let mut model: Model<load::Vertex, u16> = Model::new(wavefront).unwrap();
+            model.mesh.generate_normals(&NormalOptions { smoothing_groups: Some(&groups), ..NormalOptions::default() }).unwrap();
+            model.mesh.vertices.iter().map(|v| v.normal.unwrap()).collect::<Vec<_>>()
+        };
+
+        let smooth = normals("1");
+        assert_eq!(smooth.len(), 6);
+        assert_close(smooth[0], Vector(0.0, 1.0, 1.0).normalize());
+
+        let split = normals("off");
+        assert_eq!(split.len(), 8);
+        assert!(split.iter().all(|&n| (n - Vector(0.0, 1.0, 0.0)).length() < 1.0e-5 || (n - Vector(0.0, 0.0, 1.0)).length() < 1.0e-5));
+    }
+
+    #[test]
+    fn reports_index_overflow() {
+        let mut mesh: TriangularMesh<load::Vertex, u8> = build::unit_cube();
+        let before = mesh.clone();
+        let mut many = TriangularMesh::<load::Vertex, u8>::empty();
+        for _ in 0..11 {
+            let offset = many.vertices.len() as u64;
+            many.vertices.extend(before.vertices.iter().cloned());
+            many.indices.extend(before.indices.iter().map(|&i| u64::from(i) + offset).map(|i| u8::from_u64(i).unwrap()));
+        }
+        let copy = many.clone();
+
+        match many.generate_flat_normals() {
+            Err(Error(ErrorKind::IndexTooSmall(..), _)) => (),
+            result => panic!("expected an index overflow but got {:?}", result),
+        }
+        assert_eq!(many, copy);
+        assert_eq!(mesh.generate_flat_normals().map(|o| o.len()).ok(), Some(24));
+        assert!(mesh.vertices.iter().all(|v| v.normal().is_some()));
+    }
+}
@@ -0,0 +1,520 @@
+//! Generating tangents with the MikkTSpace algorithm.
+//!
+//! This follows the reference implementation by Morten S. Mikkelsen, which
+//! most bakers use for normal maps, with the default angular threshold. The
+//! reference reads every face's corners separately, so vertices are first
+//! merged by value, and the tangent spaces it gives each corner are written
+//! back to the indexed mesh.
+
+use {TriangularMesh, HasNormal, HasTexCoords, HasTangent, Vector, Index, Error, ErrorKind};
+
+use std::collections::HashMap;
+
+/// The tangent space of a corner.
+#[derive(Copy, Clone)]
+struct TangentSpace {
+    tangent: Vector,
+    orientation_preserving: bool,
+}
+
+impl Default for TangentSpace {
+    fn default() -> Self {
+        TangentSpace { tangent: Vector(1.0, 0.0, 0.0), orientation_preserving: false }
+    }
+}
+
+/// What is known about a non-degenerate triangle.
+struct TriangleInfo {
+    /// The triangle in the mesh.
+    triangle: usize,
+    /// The triangle across each edge, where edge `i` starts at corner `i`.
+    neighbors: [Option<usize>; 3],
+    /// The group of each corner.
+    groups: [Option<usize>; 3],
+    /// The direction of increasing `u`.
+    os: Vector,
+    /// The direction of increasing `v`.
+    ot: Vector,
+    orientation_preserving: bool,
+    /// Whether the texture coordinates are too degenerate to give a
+    /// direction, so the triangle can join any group.
+    group_with_any: bool,
+}
+
+/// Triangles around a vertex that are connected by edges and agree on
+/// orientation.
+struct Group {
+    vertex: usize,
+    orientation_preserving: bool,
+    triangles: Vec<usize>,
+}
+
+fn not_zero(x: f32) -> bool {
+    x.abs() > f32::MIN_POSITIVE
+}
+
+fn vector_not_zero(v: Vector) -> bool {
+    not_zero(v.0) || not_zero(v.1) || not_zero(v.2)
+}
+
+fn normalize(v: Vector) -> Vector {
+    v * (1.0 / v.length())
+}
+
+/// Projects a vector onto the plane of a normal and normalizes it.
+fn project(v: Vector, n: Vector) -> Vector {
+    let v = v - n * n.dot(v);
+    if vector_not_zero(v) { normalize(v) } else { v }
+}
+
+/// The bits of a float, with negative zero counted as zero.
+fn bits(x: f32) -> u32 {
+    if x == 0.0 { 0 } else { x.to_bits() }
+}
+
+/// The corners of a mesh, merged where their vertices are equal.
+struct Corners {
+    positions: Vec<Vector>,
+    normals: Vec<Vector>,
+    texture_coords: Vec<[f32; 2]>,
+    /// The first corner with the same vertex as each corner.
+    shared: Vec<usize>,
+}
+
+impl Corners {
+    /// Fails with `ErrorKind::MissingAttribute` if a vertex that is used
+    /// has no normal or texture coordinates.
+    fn new<V, I>(mesh: &TriangularMesh<V,I>) -> Result<Self, Error>
+        where V: HasNormal + HasTexCoords, I: Index {
+        let missing = |attribute: &str, vertex: usize| Error::from(ErrorKind::MissingAttribute(attribute.to_owned(), vertex));
+
+        let vertices: Vec<usize> = mesh.indices.iter().map(|&i| i.into() as usize).collect();
+        let positions: Vec<Vector> = vertices.iter().map(|&i| mesh.vertices[i].position()).collect();
+        let normals = vertices.iter()
+            .map(|&i| mesh.vertices[i].normal().ok_or_else(|| missing("normal", i)))
+            .collect::<Result<Vec<Vector>, Error>>()?;
+        let texture_coords = vertices.iter()
+            .map(|&i| mesh.vertices[i].texture_coords().ok_or_else(|| missing("texture coordinates", i)))
+            .collect::<Result<Vec<[f32; 2]>, Error>>()?;
+
+        let mut first: HashMap<[u32; 8], usize> = HashMap::new();
+        let shared = (0..vertices.len()).map(|corner| {
+            let (p, n, t) = (positions[corner], normals[corner], texture_coords[corner]);
+            let key = [bits(p.0), bits(p.1), bits(p.2), bits(n.0), bits(n.1), bits(n.2), bits(t[0]), bits(t[1])];
+            *first.entry(key).or_insert(corner)
+        }).collect();
+
+        Ok(Corners { positions, normals, texture_coords, shared })
+    }
+}
+
+impl TriangleInfo {
+    fn new(triangle: usize, vertices: [usize; 3], corners: &Corners) -> Self {
+        let [v1, v2, v3] = vertices.map(|i| corners.positions[i]);
+        let [t1, t2, t3] = vertices.map(|i| corners.texture_coords[i]);
+
+        let (t21x, t21y) = (t2[0] - t1[0], t2[1] - t1[1]);
+        let (t31x, t31y) = (t3[0] - t1[0], t3[1] - t1[1]);
+        let (d1, d2) = (v2 - v1, v3 - v1);
+
+        let signed_area = t21x * t31y - t21y * t31x;
+        let os = d1 * t31y - d2 * t21y;
+        let ot = d1 * -t31x + d2 * t21x;
+        let orientation_preserving = signed_area > 0.0;
+
+        let mut info = TriangleInfo {
+            triangle,
+            neighbors: [None; 3],
+            groups: [None; 3],
+            os: Vector::ZERO,
+            ot: Vector::ZERO,
+            orientation_preserving,
+            group_with_any: true,
+        };
+
+        if not_zero(signed_area) {
+            let area = signed_area.abs();
+            let (length_s, length_t) = (os.length(), ot.length());
+            let sign = if orientation_preserving { 1.0 } else { -1.0 };
+            if not_zero(length_s) { info.os = os * (sign / length_s); }
+            if not_zero(length_t) { info.ot = ot * (sign / length_t); }
+
+            if not_zero(length_s / area) && not_zero(length_t / area) {
+                info.group_with_any = false;
+            }
+        }
+        info
+    }
+}
+
+/// Generates MikkTSpace tangents for the triangles that are not degenerate.
+struct Generator<'a> {
+    corners: &'a Corners,
+    /// The merged corners of every good triangle.
+    triangles: Vec<[usize; 3]>,
+    infos: Vec<TriangleInfo>,
+    groups: Vec<Group>,
+}
+
+impl<'a> Generator<'a> {
+    fn corner_of(&self, triangle: usize, vertex: usize) -> usize {
+        self.triangles[triangle].iter().position(|&v| v == vertex).expect("the triangle uses the vertex")
+    }
+
+    /// Pairs up triangles that share an edge in opposite directions.
+    fn build_neighbors(&mut self) {
+        let mut edges: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+        for (t, vertices) in self.triangles.iter().enumerate() {
+            for i in 0..3 {
+                edges.entry((vertices[i], vertices[(i + 1) % 3])).or_default().push((t, i));
+            }
+        }
+
+        for f in 0..self.triangles.len() {
+            for i in 0..3 {
+                if self.infos[f].neighbors[i].is_some() {
+                    continue;
+                }
+                let (a, b) = (self.triangles[f][i], self.triangles[f][(i + 1) % 3]);
+                let found = edges.get(&(b, a)).and_then(|candidates| {
+                    candidates.iter().cloned().find(|&(t, j)| t != f && self.infos[t].neighbors[j].is_none())
+                });
+                if let Some((t, j)) = found {
+                    self.infos[f].neighbors[i] = Some(t);
+                    self.infos[t].neighbors[j] = Some(f);
+                }
+            }
+        }
+    }
+
+    /// Splits the corners around every vertex into groups.
+    fn build_groups(&mut self) {
+        for f in 0..self.triangles.len() {
+            for i in 0..3 {
+                if self.infos[f].group_with_any || self.infos[f].groups[i].is_some() {
+                    continue;
+                }
+
+                let group = self.groups.len();
+                self.groups.push(Group {
+                    vertex: self.triangles[f][i],
+                    orientation_preserving: self.infos[f].orientation_preserving,
+                    triangles: vec![f],
+                });
+                self.infos[f].groups[i] = Some(group);
+
+                let (left, right) = (self.infos[f].neighbors[i], self.infos[f].neighbors[(i + 2) % 3]);
+                for neighbor in left.into_iter().chain(right) {
+                    self.assign(neighbor, group);
+                }
+            }
+        }
+    }
+
+    /// Adds a triangle and its neighbours around the group's vertex to the
+    /// group, returning whether the triangle joined it.
+    fn assign(&mut self, triangle: usize, group: usize) -> bool {
+        let i = self.corner_of(triangle, self.groups[group].vertex);
+        if let Some(existing) = self.infos[triangle].groups[i] {
+            return existing == group;
+        }
+
+        // The first group to reach a triangle with no orientation of its
+        // own decides it, which is the only order dependency.
+        let info = &mut self.infos[triangle];
+        if info.group_with_any && info.groups.iter().all(Option::is_none) {
+            info.orientation_preserving = self.groups[group].orientation_preserving;
+        }
+        if info.orientation_preserving != self.groups[group].orientation_preserving {
+            return false;
+        }
+
+        self.groups[group].triangles.push(triangle);
+        self.infos[triangle].groups[i] = Some(group);
+
+        let (left, right) = (self.infos[triangle].neighbors[i], self.infos[triangle].neighbors[(i + 2) % 3]);
+        for neighbor in left.into_iter().chain(right) {
+            self.assign(neighbor, group);
+        }
+        true
+    }
+
+    /// Averages the tangents of triangles around a vertex, weighted by the
+    /// angle of their corners.
+    fn evaluate(&self, members: &[usize], vertex: usize) -> Vector {
+        let mut tangent = Vector::ZERO;
+        for &f in members.iter().filter(|&&f| !self.infos[f].group_with_any) {
+            let i = self.corner_of(f, vertex);
+            let n = self.corners.normals[vertex];
+            let os = project(self.infos[f].os, n);
+
+            let corner = |j: usize| self.corners.positions[self.triangles[f][j]];
+            let (p0, p1, p2) = (corner((i + 2) % 3), corner(i), corner((i + 1) % 3));
+            let v1 = project(p0 - p1, n);
+            let v2 = project(p2 - p1, n);
+
+            let cos = v1.dot(v2).clamp(-1.0, 1.0);
+            let angle = (cos as f64).acos() as f32;
+            tangent += os * angle;
+        }
+
+        if vector_not_zero(tangent) { normalize(tangent) } else { tangent }
+    }
+
+    /// Finds the tangent space of every corner of the good triangles.
+    fn tangent_spaces(&self, spaces: &mut [TangentSpace]) {
+        // With the default angular threshold of 180 degrees, only tangents
+        // pointing in exactly opposite directions are kept apart.
+        let threshold = -1.0;
+
+        for (g, group) in self.groups.iter().enumerate() {
+            let mut subgroups: Vec<(Vec<usize>, Vector)> = Vec::new();
+            let n = self.corners.normals[group.vertex];
+
+            for &f in &group.triangles {
+                let index = self.infos[f].groups.iter().position(|&group| group == Some(g)).expect("the triangle is in the group");
+                let os = project(self.infos[f].os, n);
+                let ot = project(self.infos[f].ot, n);
+
+                let mut members: Vec<usize> = group.triangles.iter().cloned().filter(|&t| {
+                    let any = self.infos[f].group_with_any || self.infos[t].group_with_any;
+                    let (os2, ot2) = (project(self.infos[t].os, n), project(self.infos[t].ot, n));
+                    any || t == f || (os.dot(os2) > threshold && ot.dot(ot2) > threshold)
+                }).collect();
+                members.sort_unstable();
+
+                let tangent = match subgroups.iter().find(|(other, _)| *other == members) {
+                    Some(&(_, tangent)) => tangent,
+                    None => {
+                        let tangent = self.evaluate(&members, group.vertex);
+                        subgroups.push((members, tangent));
+                        tangent
+                    },
+                };
+
+                spaces[self.infos[f].triangle * 3 + index] = TangentSpace {
+                    tangent,
+                    orientation_preserving: group.orientation_preserving,
+                };
+            }
+        }
+    }
+}
+
+impl<V, I> TriangularMesh<V,I>
+    where V: HasNormal + HasTexCoords + HasTangent, I: Index {
+    /// Sets the tangent of every vertex with the MikkTSpace algorithm,
+    /// returning the original index of every vertex.
+    ///
+    /// The bitangent sign is stored in the `w` component of the tangent, so
+    /// that the bitangent is `w * normal.cross(tangent)`, as glTF expects.
+    /// Normals should already be normalized, and are left as they are.
+    ///
+    /// Vertices whose corners are given different tangents, such as where
+    /// texture coordinates are mirrored, are split, with the copies added to
+    /// the end of the vertex list. Fails with `ErrorKind::IndexTooSmall`,
+    /// leaving the mesh unchanged, if the copies cannot be addressed by the
+    /// index type, and with `ErrorKind::MissingAttribute` if a vertex has
+    /// no normal or texture coordinates.
+    pub fn generate_tangents(&mut self) -> Result<Vec<usize>, Error> {
+        let corners = Corners::new(self)?;
+        let triangle_count = self.indices.len() / 3;
+        let merged = |t: usize| [corners.shared[t * 3], corners.shared[t * 3 + 1], corners.shared[t * 3 + 2]];
+
+        let degenerate = |t: usize| {
+            let [a, b, c] = merged(t).map(|i| corners.positions[i]);
+            a == b || a == c || b == c
+        };
+        let (good, degenerate): (Vec<usize>, Vec<usize>) = (0..triangle_count).partition(|&t| !degenerate(t));
+
+        let mut generator = Generator {
+            corners: &corners,
+            triangles: good.iter().map(|&t| merged(t)).collect(),
+            infos: good.iter().map(|&t| TriangleInfo::new(t, merged(t), &corners)).collect(),
+            groups: Vec::new(),
+        };
+        generator.build_neighbors();
+        generator.build_groups();
+
+        let mut spaces = vec![TangentSpace::default(); triangle_count * 3];
+        generator.tangent_spaces(&mut spaces);
+
+        // Degenerate triangles take the tangent space of the first good
+        // corner with the same vertex.
+        for &t in &degenerate {
+            for (i, vertex) in merged(t).iter().enumerate() {
+                let source = generator.triangles.iter().flatten().position(|v| v == vertex);
+                if let Some(source) = source {
+                    spaces[t * 3 + i] = spaces[good[source / 3] * 3 + source % 3];
+                }
+            }
+        }
+
+        let tangents: Vec<[f32; 4]> = spaces.iter().map(|space| {
+            let Vector(x, y, z) = space.tangent;
+            [x, y, z, if space.orientation_preserving { 1.0 } else { -1.0 }]
+        }).collect();
+        let same = |a: &[f32; 4], b: &[f32; 4]| {
+            a[3] == b[3] && (Vector(a[0], a[1], a[2]) - Vector(b[0], b[1], b[2])).length() <= 1.0e-6
+        };
+        super::set_corner_attributes(self, &tangents, same, V::set_tangent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use load;
+    use std::f32::consts::PI;
+
+    fn vertex(position: Vector, normal: Vector, texture_coords: [f32; 2]) -> load::Vertex {
+        load::Vertex { position, normal: Some(normal), texture_coords: Some(texture_coords), color: None, tangent: None }
+    }
+
+    fn assert_close(a: [f32; 4], b: [f32; 4]) {
+        let distance = a.iter().zip(b.iter()).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt();
+        assert!(distance < 1.0e-5, "{:?} is not {:?}", a, b);
+    }
+
+    /// Checks the tangent of every vertex against the reference output for
+    /// the side of the cube it is on, and that the bitangent follows `v`.
+    #[cfg(any(feature = "wavefront", feature = "gltf"))]
+    fn check_cube(mesh: &TriangularMesh<load::Vertex, u32>, reference: &[(Vector, [f32; 4])]) {
+        for vertex in &mesh.vertices {
+            let &(_, tangent) = reference.iter().find(|&&(normal, _)| normal == vertex.normal.unwrap()).unwrap();
+            assert_close(vertex.tangent.unwrap(), tangent);
+        }
+
+        for triangle in mesh.triangles() {
+            let [a, b, c] = triangle.vertices;
+            let (d1, d2) = (b.position - a.position, c.position - a.position);
+            let (t1, t2) = (b.texture_coords.unwrap(), c.texture_coords.unwrap());
+            let (s1, s2) = ([t1[0] - a.texture_coords.unwrap()[0], t1[1] - a.texture_coords.unwrap()[1]],
+                            [t2[0] - a.texture_coords.unwrap()[0], t2[1] - a.texture_coords.unwrap()[1]]);
+            let dv = (d2 * s1[0] - d1 * s2[0]) * (s1[0] * s2[1] - s1[1] * s2[0]).signum();
+
+            let t = a.tangent.unwrap();
+            let bitangent = a.normal.unwrap().cross(Vector(t[0], t[1], t[2])) * t[3];
+            assert!(bitangent.dot(dv.normalize()) > 0.999, "{:?} does not follow {:?}", bitangent, dv);
+        }
+    }
+
+    #[cfg(feature = "wavefront")]
+    #[test]
+    fn matches_reference_for_crate_obj() {
+        use Model;
+
+        let mut model: Model<load::Vertex, u32> = Model::new(load::wavefront::from_path("res/crate.obj").unwrap()).unwrap();
+        assert_eq!(model.mesh.generate_tangents().unwrap().len(), 24);
+
+        check_cube(&model.mesh, &[
+            (Vector(0.0, -1.0, 0.0), [0.0, 0.0, 1.0, 1.0]),
+            (Vector(0.0, 1.0, 0.0), [-1.0, 0.0, 0.0, 1.0]),
+            (Vector(1.0, 0.0, 0.0), [0.0, 1.0, 0.0, 1.0]),
+            (Vector(0.0, 0.0, 1.0), [0.0, 1.0, 0.0, 1.0]),
+            (Vector(-1.0, 0.0, 0.0), [0.0, 1.0, 0.0, 1.0]),
+            (Vector(0.0, 0.0, -1.0), [0.0, -1.0, 0.0, 1.0]),
+        ]);
+    }
+
+    #[cfg(feature = "gltf")]
+    #[test]
+    fn matches_reference_for_cube_gltf() {
+        use Model;
+
+        let gltf = load::gltf::from_path("res/cube.gltf").unwrap();
+        let mut model: Model<load::Vertex, u32> = Model::new(gltf.meshes().next().unwrap()).unwrap();
+        assert_eq!(model.mesh.generate_tangents().unwrap().len(), 24);
+
+        // The texture coordinates of glTF start at the top, which flips the
+        // bitangents.
+        check_cube(&model.mesh, &[
+            (Vector(1.0, 0.0, 0.0), [0.0, 0.0, -1.0, -1.0]),
+            (Vector(-1.0, 0.0, 0.0), [0.0, 0.0, 1.0, -1.0]),
+            (Vector(0.0, 1.0, 0.0), [1.0, 0.0, 0.0, -1.0]),
+            (Vector(0.0, -1.0, 0.0), [1.0, 0.0, 0.0, -1.0]),
+            (Vector(0.0, 0.0, 1.0), [1.0, 0.0, 0.0, -1.0]),
+            (Vector(0.0, 0.0, -1.0), [-1.0, 0.0, 0.0, -1.0]),
+        ]);
+    }
+
+    #[test]
+    fn averages_tangents_around_smooth_vertices() {
+        // A smooth cylinder, with `u` going around it and a seam at `u = 0`.
+        let sides = 12;
+        let mut mesh = TriangularMesh::<load::Vertex, u16>::empty();
+        for i in 0..=sides {
+            let angle = 2.0 * PI * i as f32 / sides as f32;
+            let normal = Vector(angle.cos(), 0.0, -angle.sin());
+            for &y in &[0.0, 1.0] {
+                mesh.vertices.push(vertex(normal + Vector(0.0, y, 0.0), normal, [i as f32 / sides as f32, y]));
+            }
+        }
+        for i in 0..sides as u16 {
+            let (a, b, c, d) = (i * 2, i * 2 + 2, i * 2 + 3, i * 2 + 1);
+            mesh.indices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+
+        assert_eq!(mesh.generate_tangents().unwrap().len(), mesh.vertices.len());
+        for vertex in &mesh.vertices {
+            let n = vertex.normal.unwrap();
+            assert_close(vertex.tangent.unwrap(), [n.2, 0.0, -n.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn splits_vertices_with_mirrored_texture_coords() {
+        // Two quads whose texture coordinates are mirrored across the edge
+        // between them.
+        let up = Vector(0.0, 0.0, 1.0);
+        let mut mesh = TriangularMesh::<load::Vertex, u16> {
+            vertices: vec![
+                vertex(Vector(-1.0, 0.0, 0.0), up, [0.0, 0.0]), vertex(Vector(0.0, 0.0, 0.0), up, [1.0, 0.0]),
+                vertex(Vector(0.0, 1.0, 0.0), up, [1.0, 1.0]), vertex(Vector(-1.0, 1.0, 0.0), up, [0.0, 1.0]),
+                vertex(Vector(1.0, 0.0, 0.0), up, [0.0, 0.0]), vertex(Vector(1.0, 1.0, 0.0), up, [0.0, 1.0]),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3, 1, 4, 5, 1, 5, 2],
+        };
+
+        let original = mesh.generate_tangents().unwrap();
+        assert_eq!(original, vec![0, 1, 2, 3, 4, 5, 1, 2]);
+        for (i, vertex) in mesh.vertices.iter().enumerate() {
+            let left = i < 4;
+            assert_close(vertex.tangent.unwrap(), if left { [1.0, 0.0, 0.0, 1.0] } else { [-1.0, 0.0, 0.0, -1.0] });
+        }
+    }
+
+    #[test]
+    fn copies_tangents_to_degenerate_triangles() {
+        let up = Vector(0.0, 0.0, 1.0);
+        let mut mesh = TriangularMesh::<load::Vertex, u16> {
+            vertices: vec![
+                vertex(Vector(0.0, 0.0, 0.0), up, [0.0, 0.0]), vertex(Vector(0.0, 1.0, 0.0), up, [1.0, 0.0]),
+                vertex(Vector(-1.0, 0.0, 0.0), up, [0.0, 1.0]), vertex(Vector(0.0, 0.0, 0.0), up, [0.0, 0.0]),
+            ],
+            indices: vec![0, 3, 1, 0, 1, 2],
+        };
+
+        assert_eq!(mesh.generate_tangents().unwrap().len(), 4);
+        for vertex in &mesh.vertices {
+            assert_close(vertex.tangent.unwrap(), [0.0, 1.0, 0.0, 1.0]);
+        }
+        assert!(mesh.vertices.iter().all(|v| v.tangent.is_some()));
+    }
+
+    #[test]
+    fn reports_missing_attributes() {
+        let up = Vector(0.0, 0.0, 1.0);
+        let mut mesh = TriangularMesh::<load::Vertex, u16> {
+            vertices: vec![vertex(Vector(0.0, 0.0, 0.0), up, [0.0, 0.0]), vertex(Vector(1.0, 0.0, 0.0), up, [1.0, 0.0]), Vector(0.0, 1.0, 0.0).into()],
+            indices: vec![0, 1, 2],
+        };
+        let original = mesh.clone();
+
+        match mesh.generate_tangents() {
+            Err(Error(ErrorKind::MissingAttribute(_, 2), _)) => (),
+            result => panic!("expected a missing attribute but got {:?}", result),
+        }
+        assert_eq!(mesh, original);
+    }
+}
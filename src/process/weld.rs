@@ -0,0 +1,201 @@
+//! Merging vertices that are nearly equal.
+
+use {TriangularMesh, Vertex, Vector, Index};
+
+use std::collections::HashMap;
+
+/// How close vertices need to be for `TriangularMesh::weld` to merge them.
+///
+/// Attributes with no tolerance are ignored, so vertices that differ only
+/// in them are merged and keep the attributes of the first one.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WeldOptions {
+    /// The largest distance between merged positions.
+    pub position_tolerance: f32,
+    /// The largest angle between merged normals, in radians.
+    pub normal_angle: Option<f32>,
+    /// The largest distance between merged texture coordinates.
+    ///
+    /// Keeping this small stops UV seams from being welded shut.
+    pub texture_coords_tolerance: Option<f32>,
+    /// The largest distance between merged colors.
+    pub color_tolerance: Option<f32>,
+}
+
+impl Default for WeldOptions {
+    /// Merges vertices that are equal up to float noise in every attribute.
+    fn default() -> Self {
+        WeldOptions {
+            position_tolerance: 1.0e-5,
+            normal_angle: Some(1.0e-3),
+            texture_coords_tolerance: Some(1.0e-5),
+            color_tolerance: Some(1.0e-3),
+        }
+    }
+}
+
+impl WeldOptions {
+    /// Checks whether the attributes of two vertices are close enough to merge.
+    fn attributes_match<V: Vertex>(&self, a: &V, b: &V) -> bool {
+        let normals = self.normal_angle.is_none_or(|angle| match (a.normal(), b.normal()) {
+            (Some(n), Some(m)) => {
+                let cos = n.normalize().dot(m.normalize()).clamp(-1.0, 1.0);
+                cos.acos() <= angle
+            },
+            (n, m) => n.is_none() && m.is_none(),
+        });
+        let texture_coords = self.texture_coords_tolerance.is_none_or(|tolerance| match (a.texture_coords(), b.texture_coords()) {
+            (Some(s), Some(t)) => ((s[0] - t[0]).powi(2) + (s[1] - t[1]).powi(2)).sqrt() <= tolerance,
+            (s, t) => s.is_none() && t.is_none(),
+        });
+        let colors = self.color_tolerance.is_none_or(|tolerance| match (a.color(), b.color()) {
+            (Some(c), Some(d)) => (Vector(c.0, c.1, c.2) - Vector(d.0, d.1, d.2)).length() <= tolerance,
+            (c, d) => c.is_none() && d.is_none(),
+        });
+
+        normals && texture_coords && colors
+    }
+}
+
+/// A cell of the grid that vertices are sorted into.
+type Cell = (i64, i64, i64);
+
+impl<V: Vertex, I: Index> TriangularMesh<V,I> {
+    /// Merges vertices whose positions and attributes are within the
+    /// tolerances of the options, returning the new index of every old
+    /// vertex.
+    ///
+    /// Each merged vertex takes the attributes of the first vertex in its
+    /// group, and vertices keep their first-seen order. Triangles are never
+    /// removed, even if they become degenerate, so ranges of the index list
+    /// stay valid.
+    pub fn weld(&mut self, options: &WeldOptions) -> Vec<usize> {
+        // Vertices are only compared with the ones in neighbouring cells,
+        // which keeps the pass close to linear.
+        let cell_size = options.position_tolerance;
+        let cell = |p: Vector| -> Cell {
+            if cell_size > 0.0 {
+                ((p.0 / cell_size).floor() as i64, (p.1 / cell_size).floor() as i64, (p.2 / cell_size).floor() as i64)
+            } else {
+                (p.0.to_bits() as i64, p.1.to_bits() as i64, p.2.to_bits() as i64)
+            }
+        };
+
+        let reach = if cell_size > 0.0 { 1 } else { 0 };
+        let offsets: Vec<Cell> = (-reach..=reach)
+            .flat_map(|x| (-reach..=reach).flat_map(move |y| (-reach..=reach).map(move |z| (x, y, z))))
+            .collect();
+
+        let mut grid: HashMap<Cell, Vec<usize>> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.vertices.len());
+        let mut kept: Vec<V> = Vec::new();
+
+        for vertex in self.vertices.drain(..) {
+            let position = vertex.position();
+            let (x, y, z) = cell(position);
+
+            let existing = offsets.iter()
+                .filter_map(|&(dx, dy, dz)| grid.get(&(x + dx, y + dy, z + dz)))
+                .flat_map(|candidates| candidates.iter().cloned())
+                .filter(|&candidate| {
+                    let other = &kept[candidate];
+                    (other.position() - position).length() <= options.position_tolerance && options.attributes_match(other, &vertex)
+                })
+                .min();
+
+            match existing {
+                Some(index) => remap.push(index),
+                None => {
+                    remap.push(kept.len());
+                    grid.entry((x, y, z)).or_default().push(kept.len());
+                    kept.push(vertex);
+                },
+            }
+        }
+
+        self.vertices = kept;
+        for index in self.indices.iter_mut() {
+            let old: u64 = (*index).into();
+            *index = I::from_u64(remap[old as usize] as u64).expect("welding never adds vertices");
+        }
+
+        remap
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Triangle, HasNormal, HasTexCoords};
+    use load;
+
+    fn vertex(x: f32, y: f32, uv: [f32; 2], normal: Vector) -> load::Vertex {
+        let mut vertex = load::Vertex::from(Vector(x, y, 0.0));
+        vertex.set_texture_coords(uv);
+        vertex.set_normal(normal);
+        vertex
+    }
+
+    /// Two triangles sharing an edge, with every vertex separate.
+    fn quad(noise: f32, right_uv: f32, right_normal: Vector) -> TriangularMesh<load::Vertex, u16> {
+        let up = Vector(0.0, 0.0, 1.0);
+        TriangularMesh {
+            vertices: vec![
+                vertex(0.0, 0.0, [0.0, 0.0], up), vertex(1.0, 0.0, [1.0, 0.0], up), vertex(1.0, 1.0, [1.0, 1.0], up),
+                vertex(noise, noise, [right_uv, 0.0], right_normal), vertex(1.0 - noise, 1.0, [1.0, 1.0], right_normal),
+                vertex(0.0, 1.0, [0.0, 1.0], right_normal),
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5],
+        }
+    }
+
+    #[test]
+    fn merges_positions_within_tolerance() {
+        let mut mesh = quad(1.0e-6, 0.0, Vector(0.0, 0.0, 1.0));
+        let original: Vec<_> = mesh.triangles().collect();
+
+        let remap = mesh.weld(&WeldOptions::default());
+        assert_eq!(remap, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.triangles().next(), original.into_iter().next());
+    }
+
+    #[test]
+    fn keeps_seams_and_hard_edges() {
+        let mut seam = quad(0.0, 0.5, Vector(0.0, 0.0, 1.0));
+        assert_eq!(seam.weld(&WeldOptions::default()), vec![0, 1, 2, 3, 2, 4]);
+
+        let mut ignoring_uvs = quad(0.0, 0.5, Vector(0.0, 0.0, 1.0));
+        let options = WeldOptions { texture_coords_tolerance: None, ..WeldOptions::default() };
+        assert_eq!(ignoring_uvs.weld(&options), vec![0, 1, 2, 0, 2, 3]);
+
+        let tilted = Vector(0.0, 0.3, 1.0).normalize();
+        let mut hard = quad(0.0, 0.0, tilted);
+        assert_eq!(hard.weld(&WeldOptions::default()).iter().max(), Some(&5));
+
+        let mut soft = quad(0.0, 0.0, tilted);
+        let options = WeldOptions { normal_angle: Some(0.5), ..WeldOptions::default() };
+        assert_eq!(soft.weld(&options), vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn welds_a_large_grid() {
+        let size = 100;
+        let triangles = (0..size * size).flat_map(|i| {
+            let (x, y) = ((i % size) as f32, (i / size) as f32);
+            // A small offset on every third cell stands in for float noise.
+            let p = |dx: f32, dy: f32| Vector(x + dx, y + dy + if (i % 3) == 0 { 1.0e-5 } else { 0.0 }, 0.0);
+            vec![
+                Triangle { vertices: [p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0)] },
+                Triangle { vertices: [p(0.0, 0.0), p(1.0, 1.0), p(0.0, 1.0)] },
+            ]
+        });
+        let mut mesh: TriangularMesh<Vector, u32> = triangles.collect();
+        assert!(mesh.vertices.len() > (size + 1) * (size + 1));
+
+        let remap = mesh.weld(&WeldOptions { position_tolerance: 1.0e-4, ..WeldOptions::default() });
+        assert_eq!(mesh.vertices.len(), (size + 1) * (size + 1));
+        assert_eq!(remap.iter().max(), Some(&(mesh.vertices.len() - 1)));
+    }
+}
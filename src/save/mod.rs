@@ -0,0 +1,10 @@
+//! Writers for the different mesh formats.
+
+#[cfg(feature = "wavefront")]
+pub mod wavefront;
+
+#[cfg(feature = "stl")]
+pub mod stl;
+
+#[cfg(feature = "ply")]
+pub mod ply;
@@ -0,0 +1,188 @@
+//! Writer for the PLY (Stanford polygon) file format.
+//!
+//! Normals, texture coordinates and colors are written if every vertex
+//! in the mesh has them. Colors are stored as `uchar` channels.
+
+use {TriangularMesh, Vertex, Index, Error, ErrorKind};
+use load::ply::Format;
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Saves a mesh to a binary little-endian PLY file on disk.
+pub fn to_path<V, I, P>(mesh: &TriangularMesh<V, I>, path: P) -> Result<(), Error>
+    where V: Vertex, I: Index, P: AsRef<Path> {
+    let mut output = BufWriter::new(File::create(path.as_ref())?);
+    to_writer(mesh, Format::BinaryLittleEndian, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Writes a mesh as PLY in the given encoding.
+pub fn to_writer<V, I, W>(mesh: &TriangularMesh<V, I>, format: Format, output: &mut W) -> Result<(), Error>
+    where V: Vertex, I: Index, W: Write {
+    let vertices = &mesh.vertices;
+    let has_normals = vertices.iter().all(|v| v.normal().is_some());
+    let has_texture_coords = vertices.iter().all(|v| v.texture_coords().is_some());
+    let has_colors = vertices.iter().all(|v| v.color().is_some());
+
+    writeln!(output, "ply")?;
+    writeln!(output, "format {} 1.0", match format {
+        Format::Ascii => "ascii",
+        Format::BinaryLittleEndian => "binary_little_endian",
+        Format::BinaryBigEndian => "binary_big_endian",
+    })?;
+    writeln!(output, "comment written by mash")?;
+
+    writeln!(output, "element vertex {}", vertices.len())?;
+    let mut float_properties = vec!["x", "y", "z"];
+    if has_normals { float_properties.extend(&["nx", "ny", "nz"]) }
+    if has_texture_coords { float_properties.extend(&["s", "t"]) }
+    for name in float_properties.iter() {
+        writeln!(output, "property float {}", name)?;
+    }
+    if has_colors {
+        writeln!(output, "property uchar red\nproperty uchar green\nproperty uchar blue")?;
+    }
+
+    writeln!(output, "element face {}", mesh.indices.len() / 3)?;
+    writeln!(output, "property list uchar uint vertex_indices")?;
+    writeln!(output, "end_header")?;
+
+    let mut body = Body { format, output };
+
+    for vertex in vertices.iter() {
+        let p = vertex.position();
+        let mut floats = vec![p.0, p.1, p.2];
+        if let (true, Some(n)) = (has_normals, vertex.normal()) { floats.extend(&[n.0, n.1, n.2]) }
+        if let (true, Some(uv)) = (has_texture_coords, vertex.texture_coords()) { floats.extend(&uv) }
+
+        for &value in floats.iter() {
+            body.write_f32(value)?;
+        }
+        if let (true, Some(color)) = (has_colors, vertex.color()) {
+            for &channel in [color.0, color.1, color.2].iter() {
+                body.write_u8((channel.clamp(0.0, 1.0) * 255.0).round() as u8)?;
+            }
+        }
+        body.end_record()?;
+    }
+
+    for triangle in mesh.indices.chunks(3) {
+        body.write_u8(triangle.len() as u8)?;
+        for &index in triangle {
+            let index: u64 = index.into();
+            body.write_u32(u32::try_from(index).map_err(|_| ErrorKind::IndexTooSmall(index, 32))?)?;
+        }
+        body.end_record()?;
+    }
+
+    Ok(())
+}
+
+/// Writes the values of the body in either text or binary.
+struct Body<'a, W: Write + 'a> {
+    format: Format,
+    output: &'a mut W,
+}
+
+impl<'a, W: Write> Body<'a, W> {
+    fn write_f32(&mut self, value: f32) -> Result<(), Error> {
+        match self.format {
+            Format::Ascii => write!(self.output, "{} ", value)?,
+            Format::BinaryLittleEndian => self.output.write_all(&value.to_le_bytes())?,
+            Format::BinaryBigEndian => self.output.write_all(&value.to_be_bytes())?,
+        }
+        Ok(())
+    }
+
+    fn write_u32(&mut self, value: u32) -> Result<(), Error> {
+        match self.format {
+            Format::Ascii => write!(self.output, "{} ", value)?,
+            Format::BinaryLittleEndian => self.output.write_all(&value.to_le_bytes())?,
+            Format::BinaryBigEndian => self.output.write_all(&value.to_be_bytes())?,
+        }
+        Ok(())
+    }
+
+    fn write_u8(&mut self, value: u8) -> Result<(), Error> {
+        match self.format {
+            Format::Ascii => write!(self.output, "{} ", value)?,
+            _ => self.output.write_all(&[value])?,
+        }
+        Ok(())
+    }
+
+    fn end_record(&mut self) -> Result<(), Error> {
+        if self.format == Format::Ascii {
+            writeln!(self.output)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Model, Vector, Color};
+    use load::{self, ply};
+
+    fn cube() -> Model<load::Vertex, u32> {
+        Model::new(load::from_path("res/cube.ply").unwrap()).unwrap()
+    }
+
+    fn round_trip(format: Format) -> (ply::Ply, Model<load::Vertex, u32>) {
+        let mut bytes = Vec::new();
+        to_writer(&cube().mesh, format, &mut bytes).unwrap();
+
+        let reloaded = ply::from_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.format(), format);
+
+        let model = Model::new(load::LoadedFile::Ply(ply::from_bytes(&bytes).unwrap())).unwrap();
+        (reloaded, model)
+    }
+
+    #[test]
+    fn round_trips_in_every_encoding() {
+        for &format in [Format::Ascii, Format::BinaryLittleEndian, Format::BinaryBigEndian].iter() {
+            let (_, model) = round_trip(format);
+            assert_eq!(model, cube());
+        }
+    }
+
+    #[test]
+    fn rejects_indices_that_do_not_fit() {
+        let vertices = vec![Vector(0.0, 0.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0)];
+        let mesh: TriangularMesh<Vector, u64> = TriangularMesh { vertices, indices: vec![0, 1, 1 << 32] };
+
+        match to_writer(&mesh, Format::Ascii, &mut Vec::new()) {
+            Err(Error(ErrorKind::IndexTooSmall(index, 32), _)) => assert_eq!(index, 1 << 32),
+            result => panic!("expected the index to be too large but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn writes_colors_as_bytes() {
+        let (reloaded, model) = round_trip(Format::BinaryLittleEndian);
+
+        let vertices = reloaded.vertices().unwrap();
+        assert_eq!(vertices[4].properties.get("red"), Some(&ply::Value::UChar(255)));
+        assert_eq!(vertices[4].properties.get("green"), Some(&ply::Value::UChar(0)));
+        assert_eq!(model.mesh.vertices[4].color, Some(Color(1.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn omits_missing_attributes() {
+        let model: Model<Vector, u16> = Model::new(ply::from_path("res/cube.ply").unwrap()).unwrap();
+
+        let mut bytes = Vec::new();
+        to_writer(&model.mesh, Format::Ascii, &mut bytes).unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+
+        assert!(!text.contains("red"));
+        assert!(!text.contains("nx"));
+        assert!(text.contains("element face 12"));
+    }
+}
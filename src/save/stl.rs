@@ -0,0 +1,135 @@
+//! Writer for the STL (stereolithography) file format.
+
+use {TriangularMesh, Vertex, Index, Vector, Error, ErrorKind};
+use load::stl::HEADER_SIZE;
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// The header written to binary files.
+///
+/// It must not start with `solid`, or readers may mistake the file for ASCII.
+const HEADER: &[u8] = b"binary STL written by mash";
+
+/// Saves a mesh to a binary STL file on disk.
+pub fn to_path<V, I, P>(mesh: &TriangularMesh<V, I>, path: P) -> Result<(), Error>
+    where V: Vertex, I: Index, P: AsRef<Path> {
+    let mut output = BufWriter::new(File::create(path.as_ref())?);
+    to_writer(mesh, &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Writes a mesh as binary STL.
+///
+/// Facet normals are computed from the triangle winding. Fails with
+/// `ErrorKind::TooManyTriangles` if the mesh has more triangles than the
+/// 32-bit count in the file can hold.
+pub fn to_writer<V, I, W>(mesh: &TriangularMesh<V, I>, output: &mut W) -> Result<(), Error>
+    where V: Vertex, I: Index, W: Write {
+    let mut header = [0; HEADER_SIZE];
+    header[0..HEADER.len()].copy_from_slice(HEADER);
+    output.write_all(&header)?;
+
+    let triangle_count = (mesh.indices.len() / 3) as u64;
+    let triangle_count = u32::try_from(triangle_count).map_err(|_| ErrorKind::TooManyTriangles(triangle_count, 32))?;
+    output.write_all(&triangle_count.to_le_bytes())?;
+
+    for triangle in mesh.triangles() {
+        let positions = triangle_positions(&triangle.vertices);
+
+        for vector in [facet_normal(&positions)].iter().chain(positions.iter()) {
+            for component in [vector.0, vector.1, vector.2].iter() {
+                output.write_all(&component.to_le_bytes())?;
+            }
+        }
+
+        // Attribute byte count.
+        output.write_all(&[0, 0])?;
+    }
+
+    Ok(())
+}
+
+/// Writes a mesh as ASCII STL.
+pub fn to_ascii_writer<V, I, W>(mesh: &TriangularMesh<V, I>, name: &str, output: &mut W) -> Result<(), Error>
+    where V: Vertex, I: Index, W: Write {
+    writeln!(output, "solid {}", name)?;
+
+    for triangle in mesh.triangles() {
+        let positions = triangle_positions(&triangle.vertices);
+        let n = facet_normal(&positions);
+
+        writeln!(output, "  facet normal {:e} {:e} {:e}", n.0, n.1, n.2)?;
+        writeln!(output, "    outer loop")?;
+        for p in positions.iter() {
+            writeln!(output, "      vertex {:e} {:e} {:e}", p.0, p.1, p.2)?;
+        }
+        writeln!(output, "    endloop")?;
+        writeln!(output, "  endfacet")?;
+    }
+
+    writeln!(output, "endsolid {}", name)?;
+    Ok(())
+}
+
+fn triangle_positions<V: Vertex>(vertices: &[V; 3]) -> [Vector; 3] {
+    [vertices[0].position(), vertices[1].position(), vertices[2].position()]
+}
+
+/// Computes the unit normal of a counter-clockwise triangle.
+fn facet_normal(p: &[Vector; 3]) -> Vector {
+    (p[1] - p[0]).cross(p[2] - p[0]).normalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {Model, Vector};
+    use load::stl;
+
+    fn cube() -> (stl::Stl, Model<Vector, u32>) {
+        let model = Model::new(stl::from_path("res/cube-binary.stl").unwrap()).unwrap();
+        (stl::from_path("res/cube-binary.stl").unwrap(), model)
+    }
+
+    #[test]
+    fn binary_round_trip_preserves_facets() {
+        let (original, model) = cube();
+
+        let mut bytes = Vec::new();
+        to_writer(&model.mesh, &mut bytes).unwrap();
+
+        let reloaded = stl::from_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.format(), stl::Format::Binary);
+        assert_eq!(reloaded.facets(), original.facets());
+    }
+
+    #[test]
+    fn ascii_round_trip_preserves_facets() {
+        let (original, model) = cube();
+
+        let mut bytes = Vec::new();
+        to_ascii_writer(&model.mesh, "Cube", &mut bytes).unwrap();
+
+        let reloaded = stl::from_bytes(&bytes).unwrap();
+        assert_eq!(reloaded.format(), stl::Format::Ascii);
+        assert_eq!(reloaded.name(), "Cube");
+        assert_eq!(reloaded.facets(), original.facets());
+    }
+
+    #[test]
+    fn computes_facet_normals() {
+        let mesh: TriangularMesh<Vector, u8> = TriangularMesh {
+            vertices: vec![Vector(0.0, 0.0, 0.0), Vector(2.0, 0.0, 0.0), Vector(0.0, 2.0, 0.0)],
+            indices: vec![0, 1, 2],
+        };
+
+        let mut bytes = Vec::new();
+        to_writer(&mesh, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 84 + 50);
+        assert_eq!(stl::from_bytes(&bytes).unwrap().facets()[0].normal, Vector(0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,448 @@
+//! Writer for the Wavefront `.obj` file format.
+//!
+//! Each submesh of a model is written as its own object, using its
+//! material from the model's material table, which is written to the
+//! `.mtl` material library.
+//!
+//! ```
+//! use mash::{load, Model, Vector};
+//! use mash::save::wavefront::Writer;
+//!
+//! let model: Model<Vector, u32> = Model::new(load::from_path("res/crate.obj").unwrap()).unwrap();
+//!
+//! let mut writer = Writer::new();
+//! writer.add_model("crate", &model);
+//!
+//! let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+//! writer.write_obj(&mut obj, Some("crate.mtl")).unwrap();
+//! writer.write_mtl(&mut mtl).unwrap();
+//! assert!(String::from_utf8(mtl).unwrap().starts_with("newmtl Wood\n"));
+//! ```
+
+use {Model, Material, ImageSource, Texture, Vertex, Index, Color, Error};
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Collects models to be written to a Wavefront file.
+pub struct Writer<'a, V: Vertex + 'a, I: Index + 'a> {
+    objects: Vec<Object<'a, V, I>>,
+}
+
+/// A named object to be written.
+pub struct Object<'a, V: Vertex + 'a, I: Index + 'a> {
+    name: String,
+    group: Option<String>,
+    model: &'a Model<V, I>,
+}
+
+/// The materials of every object, with the names they are written under.
+struct Library<'a> {
+    materials: Vec<(String, &'a Material)>,
+    /// The library index of each material of each object.
+    ids: Vec<Vec<usize>>,
+}
+
+/// Saves a single model to an `.obj` file on disk.
+///
+/// The object is named after the file, and its materials are written to
+/// an `.mtl` file alongside it.
+pub fn to_path<V, I, P>(model: &Model<V, I>, path: P) -> Result<(), Error>
+    where V: Vertex, I: Index, P: AsRef<Path> {
+    let path = path.as_ref();
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let mut writer = Writer::new();
+    writer.add_model(name, model);
+    writer.save(path)
+}
+
+/// Writes a single, unnamed model as `.obj` text.
+pub fn to_writer<V, I, W>(model: &Model<V, I>, output: &mut W) -> Result<(), Error>
+    where V: Vertex, I: Index, W: Write {
+    let mut writer = Writer::new();
+    writer.add_model("", model);
+    writer.write_obj(output, None)
+}
+
+impl<'a, V: Vertex, I: Index> Writer<'a, V, I> {
+    /// Creates a new writer with no objects.
+    pub fn new() -> Self {
+        Writer { objects: Vec::new() }
+    }
+
+    /// Adds a model as a named object.
+    ///
+    /// Submeshes with a name of their own are written as separate objects.
+    pub fn add_model<S>(&mut self, name: S, model: &'a Model<V, I>) -> &mut Object<'a, V, I>
+        where S: Into<String> {
+        self.objects.push(Object { name: name.into(), group: None, model });
+        self.objects.last_mut().unwrap()
+    }
+
+    /// Writes the `.obj` file.
+    ///
+    /// `material_library` is the path of the `.mtl` file that the
+    /// `.obj` file should refer to, if any.
+    pub fn write_obj<W>(&self, output: &mut W, material_library: Option<&str>) -> Result<(), Error>
+        where W: Write {
+        if let Some(material_library) = material_library {
+            writeln!(output, "mtllib {}", material_library)?;
+        }
+
+        let library = self.library();
+
+        // Indices in an OBJ file are global and one-based.
+        let (mut position_base, mut texcoord_base, mut normal_base) = (1, 1, 1);
+        // The material stays in use until the next `usemtl`, even across objects.
+        let mut current_material = None;
+
+        for (object, ids) in self.objects.iter().zip(library.ids.iter()) {
+            let vertices = &object.model.mesh.vertices;
+            let texture_coords: Option<Vec<_>> = vertices.iter().map(Vertex::texture_coords).collect();
+            let normals: Option<Vec<_>> = vertices.iter().map(Vertex::normal).collect();
+
+            let ranges = object.ranges();
+            let mut current_name = ranges.first().map_or(object.name.as_str(), |range| range.0);
+            if !current_name.is_empty() {
+                writeln!(output, "o {}", current_name)?;
+            }
+
+            // Colors are written with the common `v x y z r g b` extension.
+            let colors: Option<Vec<_>> = vertices.iter().map(Vertex::color).collect();
+            for (i, vertex) in vertices.iter().enumerate() {
+                let p = vertex.position();
+                match colors {
+                    Some(ref colors) => writeln!(output, "v {} {} {} {} {} {}", p.0, p.1, p.2, colors[i].0, colors[i].1, colors[i].2)?,
+                    None => writeln!(output, "v {} {} {}", p.0, p.1, p.2)?,
+                }
+            }
+            for uv in texture_coords.iter().flatten() {
+                writeln!(output, "vt {} {}", uv[0], uv[1])?;
+            }
+            for n in normals.iter().flatten() {
+                writeln!(output, "vn {} {} {}", n.0, n.1, n.2)?;
+            }
+
+            for (i, (name, indices, material)) in ranges.into_iter().enumerate() {
+                let starts_object = name != current_name;
+                if starts_object {
+                    writeln!(output, "o {}", name)?;
+                    current_name = name;
+                }
+                // Starting an object leaves every group.
+                if let (true, Some(group)) = (i == 0 || starts_object, object.group.as_ref()) {
+                    writeln!(output, "g {}", group)?;
+                }
+
+                if let Some(id) = material.and_then(|material| ids.get(material).cloned()) {
+                    if current_material != Some(id) {
+                        writeln!(output, "usemtl {}", library.materials[id].0)?;
+                        current_material = Some(id);
+                    }
+                }
+
+                for triangle in object.model.mesh.indices[indices].chunks(3) {
+                    write!(output, "f")?;
+
+                    for &index in triangle {
+                        let index: u64 = index.into();
+
+                        match (texture_coords.is_some(), normals.is_some()) {
+                            (false, false) => write!(output, " {}", position_base + index)?,
+                            (true, false) => write!(output, " {}/{}", position_base + index, texcoord_base + index)?,
+                            (false, true) => write!(output, " {}//{}", position_base + index, normal_base + index)?,
+                            (true, true) => write!(output, " {}/{}/{}", position_base + index, texcoord_base + index, normal_base + index)?,
+                        }
+                    }
+
+                    writeln!(output)?;
+                }
+            }
+
+            let vertex_count = vertices.len() as u64;
+            position_base += vertex_count;
+            if texture_coords.is_some() { texcoord_base += vertex_count }
+            if normals.is_some() { normal_base += vertex_count }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the `.mtl` material library, with the material table of
+    /// every model.
+    pub fn write_mtl<W>(&self, output: &mut W) -> Result<(), Error>
+        where W: Write {
+        for (i, &(ref name, material)) in self.library().materials.iter().enumerate() {
+            if i != 0 { writeln!(output)? }
+            write_material(output, name, material)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the `.obj` file to disk.
+    ///
+    /// If any of the models have materials, a `.mtl` file with the same
+    /// name is written alongside it.
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
+        where P: AsRef<Path> {
+        let path = path.as_ref();
+
+        let material_library = if self.library().materials.is_empty() {
+            None
+        } else {
+            let mtl_path = path.with_extension("mtl");
+            let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+            self.write_mtl(&mut mtl)?;
+            mtl.flush()?;
+
+            mtl_path.file_name().map(|name| name.to_string_lossy().into_owned())
+        };
+
+        let mut obj = BufWriter::new(File::create(path)?);
+        self.write_obj(&mut obj, material_library.as_deref())?;
+        obj.flush()?;
+        Ok(())
+    }
+
+    /// Gathers the materials of every object, writing identical materials
+    /// once and renaming different materials that share a name.
+    fn library(&self) -> Library<'a> {
+        let mut materials: Vec<(String, &'a Material)> = Vec::new();
+
+        let ids = self.objects.iter().map(|object| {
+            object.model.materials.iter().map(|material| {
+                if let Some(id) = materials.iter().position(|&(_, other)| other == material) {
+                    return id;
+                }
+
+                let mut name = material.name.clone();
+                let mut suffix = 1;
+                while materials.iter().any(|(other, _)| *other == name) {
+                    suffix += 1;
+                    name = format!("{}_{}", material.name, suffix);
+                }
+                materials.push((name, material));
+                materials.len() - 1
+            }).collect()
+        }).collect();
+
+        Library { materials, ids }
+    }
+}
+
+impl<'a, V: Vertex, I: Index> Default for Writer<'a, V, I> {
+    fn default() -> Self { Writer::new() }
+}
+
+impl<'a, V: Vertex, I: Index> Object<'a, V, I> {
+    /// Places the object's faces into a group.
+    pub fn group<S>(&mut self, name: S) -> &mut Self
+        where S: Into<String> {
+        self.group = Some(name.into());
+        self
+    }
+
+    /// Gets the object name, index range and material of each submesh, or
+    /// of the whole mesh if there are none.
+    fn ranges(&self) -> Vec<(&str, Range<usize>, Option<usize>)> {
+        if self.model.submeshes.is_empty() {
+            return vec![(self.name.as_str(), 0..self.model.mesh.indices.len(), None)];
+        }
+
+        self.model.submeshes.iter().map(|submesh| {
+            let name = if submesh.name.is_empty() { self.name.as_str() } else { submesh.name.as_str() };
+            (name, submesh.index_offset..submesh.index_offset + submesh.index_count, submesh.material)
+        }).collect()
+    }
+}
+
+/// Writes a material in the physically based extension of the `.mtl`
+/// format, along with the closest classic Phong parameters.
+fn write_material<W>(output: &mut W, name: &str, material: &Material) -> Result<(), Error>
+    where W: Write {
+    writeln!(output, "newmtl {}", name)?;
+
+    let colors = [("Kd", material.base_color), ("Ke", material.emissive)];
+    for &(keyword, Color(r, g, b)) in colors.iter() {
+        writeln!(output, "{} {} {} {}", keyword, r, g, b)?;
+    }
+
+    // The inverse of the mapping the loader uses for Phong exponents.
+    if material.roughness > 0.0 {
+        writeln!(output, "Ns {}", 2.0 / (material.roughness * material.roughness) - 2.0)?;
+    }
+    let factors = [("d", material.opacity), ("Pr", material.roughness), ("Pm", material.metallic)];
+    for &(keyword, factor) in factors.iter() {
+        writeln!(output, "{} {}", keyword, factor)?;
+    }
+
+    let textures = [
+        ("map_Kd", &material.base_color_texture),
+        ("map_d", &material.opacity_texture),
+        ("map_Ke", &material.emissive_texture),
+        ("map_Pr", &material.roughness_texture),
+        ("map_Pm", &material.metallic_texture),
+        ("norm", &material.normal_texture),
+    ];
+    for &(keyword, texture) in textures.iter() {
+        // Embedded images cannot be referred to from an `.mtl` file.
+        match *texture {
+            Some(Texture { source: ImageSource::Path(ref path), .. }) => writeln!(output, "{} {}", keyword, path.display())?,
+            Some(Texture { source: ImageSource::Uri(ref uri), .. }) => writeln!(output, "{} {}", keyword, uri)?,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {build, Model, Vector, HasNormal, HasTexCoords, HasColor};
+    use load::{self, wavefront};
+
+    fn cube() -> Model<Vector, u32> {
+        Model::from_mesh(build::unit_cube())
+    }
+
+    fn write(writer: &Writer<load::Vertex, u32>) -> String {
+        let mut obj = Vec::new();
+        writer.write_obj(&mut obj, None).unwrap();
+        String::from_utf8(obj).unwrap()
+    }
+
+    #[test]
+    fn can_round_trip_positions() {
+        let original = cube();
+        let mut obj = Vec::new();
+        to_writer(&original, &mut obj).unwrap();
+
+        let reloaded = wavefront::from_memory(&mut &obj[..], |_| &b""[..]).unwrap();
+        let reloaded: Model<Vector, u32> = Model::new(reloaded).unwrap();
+
+        assert_eq!(reloaded.mesh.triangles().collect::<Vec<_>>(), original.mesh.triangles().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn writes_attributes_when_present() {
+        let vertex = |x: f32, u: f32| {
+            let mut vertex = load::Vertex::from(Vector(x, 0.0, 0.0));
+            vertex.set_normal(Vector(0.0, 0.0, 1.0));
+            vertex.set_texture_coords([u, 0.5]);
+            vertex
+        };
+        let mut model = Model::from_mesh(::TriangularMesh { vertices: vec![vertex(0.0, 0.0), vertex(1.0, 1.0), vertex(2.0, 0.25)], indices: vec![0, 1, 2] });
+        let bounds = model.mesh.bounds(0..3);
+        model.materials.push(::Material::new("red".to_owned()));
+        model.submeshes.push(::Submesh { name: String::new(), index_offset: 0, index_count: 3, material: Some(0), bounds });
+
+        let mut writer = Writer::new();
+        writer.add_model("first", &model).group("grouped");
+        writer.add_model("second", &model);
+
+        assert_eq!(write(&writer), "\
+o first
+v 0 0 0
+v 1 0 0
+v 2 0 0
+vt 0 0.5
+vt 1 0.5
+vt 0.25 0.5
+vn 0 0 1
+vn 0 0 1
+vn 0 0 1
+g grouped
+usemtl red
+f 1/1/1 2/2/2 3/3/3
+o second
+v 0 0 0
+v 1 0 0
+v 2 0 0
+vt 0 0.5
+vt 1 0.5
+vt 0.25 0.5
+vn 0 0 1
+vn 0 0 1
+vn 0 0 1
+f 4/4/4 5/5/5 6/6/6
+");
+    }
+
+    #[test]
+    fn round_trips_vertex_colors() {
+        let mut original: Model<load::Vertex, u32> = Model::from_mesh(build::unit_cube());
+        for (i, vertex) in original.mesh.vertices.iter_mut().enumerate() {
+            vertex.set_color(::Color(i as f32 / 8.0, 0.5, 1.0));
+        }
+
+        let mut obj = Vec::new();
+        to_writer(&original, &mut obj).unwrap();
+        assert!(String::from_utf8(obj.clone()).unwrap().starts_with("v 1 -1 -1 0 0.5 1\n"));
+
+        let reloaded = wavefront::from_memory(&mut &obj[..], |_| &b""[..]).unwrap();
+        let reloaded: Model<load::Vertex, u32> = Model::new(reloaded).unwrap();
+        assert_eq!(reloaded.mesh.triangles().collect::<Vec<_>>(), original.mesh.triangles().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn can_write_material_library() {
+        let world = wavefront::from_path("res/world.obj").unwrap();
+        let model: Model<Vector, u32> = Model::new(world).unwrap();
+
+        let mut writer = Writer::new();
+        writer.add_model("world", &model);
+
+        let mut mtl = Vec::new();
+        writer.write_mtl(&mut mtl).unwrap();
+        let mtl = String::from_utf8(mtl).unwrap();
+        assert!(mtl.starts_with("\
+newmtl Material
+Kd 0.64 0.64 0.64
+Ke 0 0 0
+"), "{}", mtl);
+        assert!(mtl.contains("\nnewmtl None\n"), "{}", mtl);
+    }
+
+    #[test]
+    fn renames_different_materials_with_the_same_name() {
+        let mut first = cube();
+        let mut second = cube();
+        first.materials.push(::Material::new("paint".to_owned()));
+        second.materials.push(::Material { base_color: ::Color(1.0, 0.0, 0.0), ..::Material::new("paint".to_owned()) });
+        second.materials.push(::Material::new("paint".to_owned()));
+
+        let mut writer = Writer::new();
+        writer.add_model("first", &first);
+        writer.add_model("second", &second);
+
+        let library = writer.library();
+        let names: Vec<_> = library.materials.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["paint", "paint_2"]);
+        assert_eq!(library.ids, vec![vec![0], vec![1, 0]]);
+    }
+
+    #[test]
+    fn round_trips_materials_and_submeshes() {
+        for path in &["res/world.obj", "res/crate.obj"] {
+            let original: Model<load::Vertex, u32> = Model::new(wavefront::from_path(path).unwrap()).unwrap();
+
+            let mut writer = Writer::new();
+            writer.add_model("", &original);
+            let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+            writer.write_obj(&mut obj, Some("saved.mtl")).unwrap();
+            writer.write_mtl(&mut mtl).unwrap();
+
+            let reloaded = wavefront::from_memory(&mut &obj[..], |_| &mtl[..]).unwrap();
+            let reloaded: Model<load::Vertex, u32> = Model::new(reloaded).unwrap();
+
+            assert_eq!(reloaded.materials, original.materials, "{}", path);
+            assert_eq!(reloaded.submeshes, original.submeshes, "{}", path);
+            assert_eq!(reloaded.mesh.triangles().collect::<Vec<_>>(), original.mesh.triangles().collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Scenes made of instances of shared models.
+
+use {Model, TriangularMesh, Submesh, Material, Vertex, Vector, Matrix4, Index, Error, ErrorKind};
+
+/// A hierarchy of nodes that place shared models in the world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scene<V: Vertex, I: Index> {
+    /// The models that nodes refer to.
+    ///
+    /// Submesh material ids refer to `materials`, and the material
+    /// tables of the models themselves are empty.
+    pub models: Vec<Model<V, I>>,
+    /// The materials shared by every model.
+    pub materials: Vec<Material>,
+    /// Every node in the scene.
+    pub nodes: Vec<Node>,
+    /// The indices of the nodes that have no parent.
+    pub roots: Vec<usize>,
+}
+
+/// A node in a scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    /// The name of the node, which may be empty.
+    pub name: String,
+    /// The transform relative to the parent node.
+    pub transform: Matrix4,
+    /// The indices of the child nodes.
+    pub children: Vec<usize>,
+    /// The index of the model drawn at the node, if any.
+    pub model: Option<usize>,
+}
+
+/// Something which we can build a scene out of.
+pub trait BuildScene {
+    /// The vertex type that we need to convert from.
+    type Vertex;
+
+    fn build_scene<V,I>(self) -> Result<Scene<V,I>, Error>
+        where V: Vertex, I: Index, V: From<Self::Vertex>;
+}
+
+/// A vertex that can be moved into world space.
+pub trait TransformVertex: Vertex {
+    /// Gets the vertex transformed by a matrix.
+    fn transform(&self, matrix: &Matrix4) -> Self;
+}
+
+impl<V: Vertex, I: Index> Scene<V,I> {
+    /// Creates an empty scene.
+    pub fn empty() -> Self {
+        Scene { models: Vec::new(), materials: Vec::new(), nodes: Vec::new(), roots: Vec::new() }
+    }
+
+    /// Creates a new scene.
+    pub fn new<F>(builder: F) -> Result<Self, Error>
+        where F: BuildScene, V: From<F::Vertex> {
+        builder.build_scene()
+    }
+
+    /// Creates a scene with a single node that draws a model.
+    ///
+    /// The material table of the model becomes the table of the scene.
+    pub fn from_model(mut model: Model<V,I>) -> Self {
+        let mut scene = Scene::empty();
+        scene.materials = ::std::mem::take(&mut model.materials);
+
+        let model = scene.add_model(model);
+        scene.add_node(Node::new(String::new(), Matrix4::IDENTITY, Some(model)), None);
+        scene
+    }
+
+    /// Adds a model that nodes can refer to, returning its index.
+    pub fn add_model(&mut self, model: Model<V,I>) -> usize {
+        self.models.push(model);
+        self.models.len() - 1
+    }
+
+    /// Adds a node as a child of another node, or as a root node,
+    /// returning its index.
+    pub fn add_node(&mut self, node: Node, parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(node);
+
+        match parent {
+            Some(parent) => self.nodes[parent].children.push(index),
+            None => self.roots.push(index),
+        }
+        index
+    }
+
+    /// Gets every node that draws a model, along with its world transform.
+    ///
+    /// Nodes are visited depth-first, in the order of their children. Fails
+    /// with `ErrorKind::InvalidScene` if a node is its own ancestor, or if
+    /// a node or model index is out of range.
+    pub fn instances(&self) -> Result<Vec<(usize, Matrix4)>, Error> {
+        let mut instances = Vec::new();
+        // Each entry also has its depth, so that the path of ancestors
+        // leading to it can be kept.
+        let mut stack: Vec<(usize, Matrix4, usize)> = self.roots.iter().rev().map(|&root| (root, Matrix4::IDENTITY, 0)).collect();
+        let mut path = Vec::new();
+
+        while let Some((index, parent, depth)) = stack.pop() {
+            let node = self.nodes.get(index)
+                .ok_or_else(|| ErrorKind::InvalidScene(format!("node {} does not exist", index)))?;
+
+            path.truncate(depth);
+            if path.contains(&index) {
+                return Err(ErrorKind::InvalidScene(format!("node {} is its own ancestor", index)).into());
+            }
+            path.push(index);
+
+            let world = parent.multiply(&node.transform);
+            if let Some(model) = node.model {
+                if model >= self.models.len() {
+                    return Err(ErrorKind::InvalidScene(format!("node {} draws model {}, which does not exist", index, model)).into());
+                }
+                instances.push((index, world));
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, world, depth + 1)));
+        }
+
+        Ok(instances)
+    }
+
+    /// Merges every model instance into a single world-space model.
+    ///
+    /// Each submesh of each instance becomes a submesh of the model, named
+    /// after its node if the node has a name. Instances with a mirroring
+    /// transform have their winding reversed, so they still face outwards.
+    pub fn flatten(&self) -> Result<Model<V,I>, Error>
+        where V: TransformVertex {
+        let mut model = Model {
+            mesh: TriangularMesh::empty(),
+            materials: self.materials.clone(),
+            submeshes: Vec::new(),
+        };
+
+        for (index, world) in self.instances()? {
+            let node = &self.nodes[index];
+            let instance = &self.models[node.model.expect("instances always have models")];
+
+            let first_vertex = model.mesh.vertices.len() as u64;
+            let first_index = model.mesh.indices.len();
+            model.mesh.vertices.extend(instance.mesh.vertices.iter().map(|v| v.transform(&world)));
+            for &index in instance.mesh.indices.iter() {
+                let index: u64 = index.into();
+                model.mesh.indices.push(I::from_u64(first_vertex + index)?);
+            }
+            if world.determinant() < 0.0 {
+                let indices = first_index..model.mesh.indices.len();
+                model.mesh.flip_winding(indices);
+            }
+
+            for submesh in instance.submeshes.iter() {
+                let index_offset = first_index + submesh.index_offset;
+                model.submeshes.push(Submesh {
+                    name: if node.name.is_empty() { submesh.name.clone() } else { node.name.clone() },
+                    index_offset,
+                    index_count: submesh.index_count,
+                    material: submesh.material,
+                    bounds: model.mesh.bounds(index_offset..index_offset + submesh.index_count),
+                });
+            }
+        }
+
+        Ok(model)
+    }
+}
+
+impl Node {
+    /// Creates a node without any children.
+    pub fn new(name: String, transform: Matrix4, model: Option<usize>) -> Self {
+        Node { name, transform, children: Vec::new(), model }
+    }
+}
+
+impl TransformVertex for Vector {
+    fn transform(&self, matrix: &Matrix4) -> Self {
+        matrix.transform_point(*self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {build, load, HasNormal, HasTangent};
+
+    fn cube() -> Model<Vector, u16> {
+        let mut model = Model::from_mesh(build::unit_cube());
+        let index_count = model.mesh.indices.len();
+        model.submeshes.push(Submesh {
+            name: "cube".to_owned(),
+            index_offset: 0,
+            index_count,
+            material: None,
+            bounds: model.mesh.bounds(0..index_count),
+        });
+        model
+    }
+
+    fn translation(x: f32, y: f32, z: f32) -> Matrix4 {
+        Matrix4::from_translation(Vector(x, y, z))
+    }
+
+    #[test]
+    fn flattens_nested_instances() {
+        let original = cube();
+        let mut scene = Scene::empty();
+        let cube = scene.add_model(original.clone());
+
+        let parent = scene.add_node(Node::new("parent".to_owned(), translation(10.0, 0.0, 0.0), None), None);
+        scene.add_node(Node::new("first".to_owned(), Matrix4::IDENTITY, Some(cube)), Some(parent));
+        scene.add_node(Node::new(String::new(), translation(0.0, 5.0, 0.0), Some(cube)), Some(parent));
+
+        let model = scene.flatten().unwrap();
+        assert_eq!(model.mesh.vertices.len(), 2 * original.mesh.vertices.len());
+        assert_eq!(model.submeshes.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["first", "cube"]);
+        assert_eq!(model.submeshes[1].index_offset, original.mesh.indices.len());
+
+        let min = original.submeshes[0].bounds.min;
+        assert_eq!(model.submeshes[1].bounds.min, Vector(min.0 + 10.0, min.1 + 5.0, min.2));
+    }
+
+    #[test]
+    fn mirrored_instances_keep_facing_outwards() {
+        let original = cube();
+        let mut scene = Scene::from_model(original.clone());
+        scene.nodes[0].transform = Matrix4::from_scale(Vector(-1.0, 1.0, 1.0));
+
+        let model = scene.flatten().unwrap();
+        for (mirrored, triangle) in model.mesh.triangles().zip(original.mesh.triangles()) {
+            let [a, b, c] = mirrored.vertices;
+            let normal = (b - a).cross(c - a);
+            let [a, b, c] = triangle.vertices;
+            let expected = (b - a).cross(c - a);
+            assert_eq!(normal, Vector(-expected.0, expected.1, expected.2));
+        }
+    }
+
+    #[test]
+    fn mirrored_instances_keep_their_bitangents() {
+        let mut vertex = load::Vertex::from(Vector(1.0, 2.0, 0.0));
+        vertex.set_normal(Vector(0.0, 0.0, 1.0));
+        vertex.set_tangent([1.0, 0.0, 0.0, 1.0]);
+        let triangle = TriangularMesh { vertices: vec![vertex.clone(), vertex.clone(), vertex], indices: vec![0u16, 1, 2] };
+
+        let mut scene = Scene::from_model(Model::from_mesh(triangle));
+        scene.nodes[0].transform = Matrix4::from_scale(Vector(-1.0, 1.0, 1.0));
+
+        // The bitangent is `w * normal.cross(tangent)`, and mirroring in `x`
+        // leaves it pointing along `y`.
+        let flattened = scene.flatten().unwrap();
+        let vertex = &flattened.mesh.vertices[0];
+        let (n, t) = (vertex.normal.unwrap(), vertex.tangent.unwrap());
+        assert_eq!(t, [-1.0, 0.0, 0.0, -1.0]);
+        assert_eq!(n.cross(Vector(t[0], t[1], t[2])) * t[3], Vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut scene = Scene::from_model(cube());
+        let child = scene.add_node(Node::new("child".to_owned(), Matrix4::IDENTITY, None), Some(0));
+        scene.nodes[child].children.push(0);
+
+        match scene.instances() {
+            Err(Error(ErrorKind::InvalidScene(..), _)) => (),
+            result => panic!("expected an invalid scene but got {:?}", result),
+        }
+        assert!(scene.flatten().is_err());
+    }
+
+    #[test]
+    fn rejects_missing_models() {
+        let mut scene = Scene::from_model(cube());
+        scene.add_node(Node::new("missing".to_owned(), Matrix4::IDENTITY, Some(1)), Some(0));
+
+        match scene.flatten() {
+            Err(Error(ErrorKind::InvalidScene(..), _)) => (),
+            result => panic!("expected an invalid scene but got {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn allows_shared_children() {
+        let mut scene = Scene::from_model(cube());
+        let other = scene.add_node(Node::new("other".to_owned(), translation(5.0, 0.0, 0.0), None), None);
+        scene.nodes[other].children.push(0);
+
+        assert_eq!(scene.instances().unwrap(), vec![(0, Matrix4::IDENTITY), (0, translation(5.0, 0.0, 0.0))]);
+    }
+
+    #[test]
+    fn single_model_scene_flattens_to_the_model() {
+        let scene = Scene::from_model(cube());
+        assert_eq!(scene.roots.len(), 1);
+        assert_eq!(scene.flatten().unwrap(), cube());
+    }
+}